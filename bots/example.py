@@ -0,0 +1,45 @@
+#!/usr/bin/env python3
+"""A small external bot: heads for the apple, avoiding any cell that is
+already taken. Run it with
+
+    snake --opponents 1 --ai "cmd:python3 bots/example.py"
+"""
+import json
+import sys
+
+STEPS = {"up": (0, -1), "down": (0, 1), "left": (-1, 0), "right": (1, 0)}
+OPPOSITE = {"up": "down", "down": "up", "left": "right", "right": "left"}
+
+board = {}
+for line in sys.stdin:
+    message = json.loads(line)
+    if message["type"] == "start":
+        board = message
+        continue
+    me = message["snakes"][message["you"]]
+    head = me["body"][0]
+    taken = {tuple(cell) for cell in board["walls"]}
+    for snake in message["snakes"]:
+        if snake["alive"]:
+            taken.update(tuple(cell) for cell in snake["body"])
+    apple = message["apple"]
+
+    def target(direction):
+        dx, dy = STEPS[direction]
+        col, row = head[0] + dx, head[1] + dy
+        if board["wrap"]:
+            col, row = col % board["cols"], row % board["rows"]
+        return col, row
+
+    def safe(direction):
+        col, row = target(direction)
+        return (0 <= col < board["cols"] and 0 <= row < board["rows"]
+                and (col, row) not in taken)
+
+    def distance(direction):
+        col, row = target(direction)
+        return abs(col - apple[0]) + abs(row - apple[1])
+
+    moves = [d for d in STEPS if d != OPPOSITE[me["heading"]] and safe(d)]
+    move = min(moves, key=distance) if moves else None
+    print(json.dumps({"move": move}), flush=True)
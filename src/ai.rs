@@ -0,0 +1,467 @@
+use crate::controller::{Controller, Snapshot};
+use crate::game::{Bounds, Direction, GridPos};
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Left,
+    Direction::Right,
+];
+
+/// The built-in computer opponents, from weakest to strongest.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AiKind {
+    /// Heads straight for the apple, only avoiding the very next cell.
+    Greedy,
+    /// Follows the shortest path to the apple around every body.
+    Pathfinder,
+    /// Takes the shortest path only when it leaves room to get back out.
+    Survivor,
+    /// Follows a fixed tour of every cell, which always fills an open board
+    /// when playing alone.
+    Hamiltonian,
+}
+
+impl FromStr for AiKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AiKind, String> {
+        match s {
+            "greedy" => Ok(AiKind::Greedy),
+            "bfs" => Ok(AiKind::Pathfinder),
+            "survival" => Ok(AiKind::Survivor),
+            "hamilton" => Ok(AiKind::Hamiltonian),
+            _ => Err(format!(
+                "expected greedy, bfs, survival or hamilton, found {:?}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for AiKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AiKind::Greedy => "greedy",
+            AiKind::Pathfinder => "bfs",
+            AiKind::Survivor => "survival",
+            AiKind::Hamiltonian => "hamilton",
+        })
+    }
+}
+
+impl AiKind {
+    pub fn controller(self) -> Box<dyn Controller> {
+        match self {
+            AiKind::Greedy => Box::new(Greedy),
+            AiKind::Pathfinder => Box::new(Pathfinder),
+            AiKind::Survivor => Box::new(Survivor),
+            AiKind::Hamiltonian => Box::new(Hamiltonian::new()),
+        }
+    }
+}
+
+/// The board as a bot sees it: which cells are already taken by walls and
+/// snakes.
+pub struct Board {
+    bounds: Bounds,
+    blocked: Vec<bool>,
+}
+
+impl Board {
+    pub fn new(snapshot: &Snapshot) -> Board {
+        let bounds = snapshot.bounds;
+        let mut board = Board {
+            bounds,
+            blocked: vec![false; (bounds.cols * bounds.rows) as usize],
+        };
+        let bodies = snapshot
+            .players
+            .iter()
+            .filter(|p| p.alive)
+            .flat_map(|p| p.snake.body.iter());
+        for &cell in snapshot.walls.iter().chain(bodies) {
+            board.block(cell);
+        }
+        board
+    }
+    fn index(&self, pos: GridPos) -> Option<usize> {
+        if self.bounds.contains(pos) {
+            Some((pos.row * self.bounds.cols + pos.col) as usize)
+        } else {
+            None
+        }
+    }
+    pub fn block(&mut self, pos: GridPos) {
+        if let Some(i) = self.index(pos) {
+            self.blocked[i] = true;
+        }
+    }
+    pub fn is_open(&self, pos: GridPos) -> bool {
+        self.index(pos).is_some_and(|i| !self.blocked[i])
+    }
+    /// Moves from `head` that do not reverse `heading` or run into
+    /// anything, with the cells they lead to. Carrying straight on comes
+    /// first, so ties keep the snake going.
+    pub fn safe_moves(&self, head: GridPos, heading: Direction) -> Vec<(Direction, GridPos)> {
+        let mut dirs = vec![heading];
+        dirs.extend(
+            DIRECTIONS
+                .iter()
+                .filter(|&&d| d != heading && d != !heading),
+        );
+        dirs.into_iter()
+            .map(|dir| (dir, self.bounds.step(head, dir)))
+            .filter(|&(_, next)| self.is_open(next))
+            .collect()
+    }
+    /// Steps from `from` to every open cell, by breadth-first search.
+    /// `from` itself is always 0, even if it is blocked.
+    pub fn distances(&self, from: GridPos) -> Vec<Option<u32>> {
+        let mut dist = vec![None; self.blocked.len()];
+        let start = match self.index(from) {
+            Some(i) => i,
+            None => return dist,
+        };
+        dist[start] = Some(0);
+        let mut queue = VecDeque::new();
+        queue.push_back((from, 0));
+        while let Some((pos, d)) = queue.pop_front() {
+            for &dir in &DIRECTIONS {
+                let next = self.bounds.step(pos, dir);
+                if let Some(i) = self.index(next) {
+                    if !self.blocked[i] && dist[i].is_none() {
+                        dist[i] = Some(d + 1);
+                        queue.push_back((next, d + 1));
+                    }
+                }
+            }
+        }
+        dist
+    }
+    pub fn distance(&self, dist: &[Option<u32>], pos: GridPos) -> Option<u32> {
+        self.index(pos).and_then(|i| dist[i])
+    }
+    /// How many open cells can be reached from `from`, counting itself.
+    pub fn reachable(&self, from: GridPos) -> usize {
+        self.distances(from).iter().filter(|d| d.is_some()).count()
+    }
+}
+
+/// Cells apart along each axis, the short way round on a wrapping board.
+pub(crate) fn manhattan(bounds: &Bounds, a: GridPos, b: GridPos) -> i32 {
+    let (mut dx, mut dy) = ((a.col - b.col).abs(), (a.row - b.row).abs());
+    if bounds.wrap {
+        dx = dx.min(bounds.cols - dx);
+        dy = dy.min(bounds.rows - dy);
+    }
+    dx + dy
+}
+
+pub struct Greedy;
+
+impl Controller for Greedy {
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction> {
+        let board = Board::new(snapshot);
+        let snake = snapshot.snake();
+        board
+            .safe_moves(snake.head(), snake.curr_dir)
+            .into_iter()
+            .min_by_key(|&(_, next)| manhattan(&snapshot.bounds, next, snapshot.apple))
+            .map(|(dir, _)| dir)
+    }
+}
+
+pub struct Pathfinder;
+
+impl Controller for Pathfinder {
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction> {
+        let board = Board::new(snapshot);
+        let snake = snapshot.snake();
+        let moves = board.safe_moves(snake.head(), snake.curr_dir);
+        // Searching out from the apple gives every first move its path
+        // length in one pass.
+        let dist = board.distances(snapshot.apple);
+        moves
+            .iter()
+            .filter_map(|&(dir, next)| board.distance(&dist, next).map(|d| (d, dir)))
+            .min_by_key(|&(d, _)| d)
+            .map(|(_, dir)| dir)
+            .or_else(|| Greedy.next_move(snapshot))
+    }
+}
+
+pub struct Survivor;
+
+impl Controller for Survivor {
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction> {
+        let board = Board::new(snapshot);
+        let snake = snapshot.snake();
+        let moves = board.safe_moves(snake.head(), snake.curr_dir);
+        // Cells another head could also move into risk a head-on crash.
+        let contested: Vec<GridPos> = snapshot
+            .players
+            .iter()
+            .enumerate()
+            .filter(|&(i, p)| i != snapshot.me && p.alive)
+            .flat_map(|(_, p)| {
+                DIRECTIONS
+                    .iter()
+                    .map(move |&dir| snapshot.bounds.step(p.snake.head(), dir))
+            })
+            .collect();
+        let dist = board.distances(snapshot.apple);
+        let mut ranked: Vec<(bool, u32, usize, Direction)> = moves
+            .iter()
+            .map(|&(dir, next)| {
+                let room = board.reachable(next);
+                let to_apple = board.distance(&dist, next).unwrap_or(u32::MAX);
+                (contested.contains(&next), to_apple, room, dir)
+            })
+            .collect();
+        // Safe from head-ons first, then the nearest apple among moves
+        // that keep room for the whole body, then the most room.
+        let len = snake.body.len();
+        ranked.sort_by_key(|&(risky, to_apple, room, _)| {
+            (
+                risky,
+                room < len,
+                if room >= len { to_apple } else { 0 },
+                usize::MAX - room,
+            )
+        });
+        ranked.first().map(|&(_, _, _, dir)| dir)
+    }
+}
+
+/// A closed tour visiting every cell of a wall-free board once, as each
+/// cell's successor.
+struct Cycle {
+    cols: i32,
+    rows: i32,
+    next: Vec<GridPos>,
+    prev: Vec<GridPos>,
+}
+
+impl Cycle {
+    /// Row 0 runs left to right, the rest of the board is swept row by row
+    /// over columns 1 and up, and column 0 leads back to the start. That
+    /// only closes up with an even number of rows, so boards with an odd
+    /// number of rows are swept by column instead. With both odd there is
+    /// no tour at all.
+    fn new(cols: i32, rows: i32) -> Option<Cycle> {
+        let order = if rows % 2 == 0 {
+            sweep(cols, rows)
+        } else if cols % 2 == 0 {
+            sweep(rows, cols)
+                .into_iter()
+                .map(|pos| GridPos::new(pos.row, pos.col))
+                .collect()
+        } else {
+            return None;
+        };
+        let mut next = vec![GridPos::new(0, 0); order.len()];
+        let mut prev = next.clone();
+        for (i, &pos) in order.iter().enumerate() {
+            let after = order[(i + 1) % order.len()];
+            next[(pos.row * cols + pos.col) as usize] = after;
+            prev[(after.row * cols + after.col) as usize] = pos;
+        }
+        Some(Cycle {
+            cols,
+            rows,
+            next,
+            prev,
+        })
+    }
+    fn after(&self, pos: GridPos, reverse: bool) -> GridPos {
+        let i = (pos.row * self.cols + pos.col) as usize;
+        if reverse {
+            self.prev[i]
+        } else {
+            self.next[i]
+        }
+    }
+}
+
+/// The tour for `Cycle::new` on a board with an even number of rows.
+fn sweep(cols: i32, rows: i32) -> Vec<GridPos> {
+    let mut order: Vec<GridPos> = (0..cols).map(|col| GridPos::new(col, 0)).collect();
+    for row in 1..rows {
+        if row % 2 == 1 {
+            order.extend((1..cols).rev().map(|col| GridPos::new(col, row)));
+        } else {
+            order.extend((1..cols).map(|col| GridPos::new(col, row)));
+        }
+    }
+    order.extend((1..rows).rev().map(|row| GridPos::new(0, row)));
+    order
+}
+
+/// Follows a Hamiltonian cycle round the board, so the body always trails
+/// the head along the tour and the next cell is always free until the
+/// board is full. Boards with walls, or with an odd number of cells, have
+/// no such tour and get the `Survivor` instead.
+pub struct Hamiltonian {
+    cycle: Option<Cycle>,
+    /// Running the tour backwards, when the snake starts facing that way.
+    reverse: bool,
+}
+
+impl Hamiltonian {
+    pub fn new() -> Hamiltonian {
+        Hamiltonian {
+            cycle: None,
+            reverse: false,
+        }
+    }
+}
+
+impl Default for Hamiltonian {
+    fn default() -> Hamiltonian {
+        Hamiltonian::new()
+    }
+}
+
+impl Controller for Hamiltonian {
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction> {
+        let bounds = snapshot.bounds;
+        if !snapshot.walls.is_empty() {
+            return Survivor.next_move(snapshot);
+        }
+        let fits = self
+            .cycle
+            .as_ref()
+            .is_some_and(|c| (c.cols, c.rows) == (bounds.cols, bounds.rows));
+        if !fits {
+            self.cycle = Cycle::new(bounds.cols, bounds.rows);
+        }
+        let cycle = match self.cycle {
+            Some(ref cycle) => cycle,
+            None => return Survivor.next_move(snapshot),
+        };
+        let snake = snapshot.snake();
+        let head = snake.head();
+        // The tour can be run either way round; pick the one that does not
+        // double back into the neck.
+        if snake.body.get(1) == Some(&cycle.after(head, self.reverse)) {
+            self.reverse = !self.reverse;
+        }
+        let target = cycle.after(head, self.reverse);
+        DIRECTIONS
+            .iter()
+            .copied()
+            .find(|&dir| head.next_to(dir) == target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::game::{Game, Outcome};
+    use crate::level::{BuiltinLevel, Level};
+
+    /// Plays `kind` alone on `level` until the game ends or `max_ticks`
+    /// pass, checking every move it makes is safe when a safe one exists.
+    fn play(level: &Level, kind: AiKind, seed: u64, max_ticks: u64) -> Game {
+        let mut game = Game::new(level, Difficulty::Normal, seed);
+        let mut bot = kind.controller();
+        while game.outcome().is_none() && game.ticks() < max_ticks {
+            let snapshot = Snapshot::new(&game, 0);
+            let snake = snapshot.snake();
+            let safe = Board::new(&snapshot).safe_moves(snake.head(), snake.curr_dir);
+            let dir = bot.next_move(&snapshot);
+            let taken = dir
+                .filter(|&d| d != !snake.curr_dir)
+                .unwrap_or(snake.curr_dir);
+            assert!(
+                safe.is_empty() || safe.iter().any(|&(d, _)| d == taken),
+                "{} moved {:?} on tick {}",
+                kind,
+                taken,
+                game.ticks()
+            );
+            game.step(&[dir]);
+        }
+        game
+    }
+
+    #[test]
+    fn hamiltonian_fills_even_boards() {
+        for &(cols, rows, wrap) in &[(4, 4, false), (16, 15, false), (10, 8, true)] {
+            let level = Level::empty(Bounds::new(cols, rows, wrap));
+            let cells = (cols * rows) as u64;
+            let game = play(&level, AiKind::Hamiltonian, 7, cells * cells);
+            assert_eq!(
+                game.outcome(),
+                Some(Outcome::BoardFull),
+                "{}x{}",
+                cols,
+                rows
+            );
+            assert_eq!(game.players[0].snake.body.len(), cells as usize);
+        }
+    }
+
+    #[test]
+    fn hamiltonian_falls_back_to_the_survivor_on_odd_boards() {
+        let level = Level::empty(Bounds::new(7, 7, false));
+        let mut game = Game::new(&level, Difficulty::Normal, 3);
+        let mut hamiltonian = Hamiltonian::new();
+        while game.outcome().is_none() && game.ticks() < 500 {
+            let snapshot = Snapshot::new(&game, 0);
+            let dir = hamiltonian.next_move(&snapshot);
+            assert_eq!(dir, Survivor.next_move(&snapshot));
+            game.step(&[dir]);
+        }
+        assert!(game.players[0].score.val >= 10);
+    }
+
+    #[test]
+    fn bots_turn_away_from_a_wall_in_front() {
+        let level = Level::parse("---\n......\n.S#.A.\n......\n......\n").unwrap();
+        for &kind in &[AiKind::Greedy, AiKind::Pathfinder, AiKind::Survivor] {
+            let game = Game::new(&level, Difficulty::Normal, 0);
+            let dir = kind.controller().next_move(&Snapshot::new(&game, 0));
+            assert!(
+                dir == Some(Direction::Up) || dir == Some(Direction::Down),
+                "{} chose {:?}",
+                kind,
+                dir
+            );
+        }
+    }
+
+    #[test]
+    fn bots_never_hit_a_wall_when_they_can_help_it() {
+        for &kind in &[AiKind::Greedy, AiKind::Pathfinder, AiKind::Survivor] {
+            for &layout in &[
+                BuiltinLevel::Box,
+                BuiltinLevel::Cross,
+                BuiltinLevel::Corridors,
+            ] {
+                let level = layout.build(Bounds::new(20, 15, false));
+                for seed in 0..3 {
+                    play(&level, kind, seed, 2_000);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for &kind in &[
+            AiKind::Greedy,
+            AiKind::Pathfinder,
+            AiKind::Survivor,
+            AiKind::Hamiltonian,
+        ] {
+            assert_eq!(kind.to_string().parse(), Ok(kind));
+        }
+        assert!("clever".parse::<AiKind>().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+//! Plays many games of a bot with no window and prints how it did.
+//!
+//! Takes the same `--key value` settings as the game, plus `--games` and
+//! `--max-ticks`:
+//!
+//!     snake-sim --ai survival --games 5000 --layout box
+
+use snake::config::{resource_path, Config};
+use snake::controller::Controller;
+use snake::game::Outcome;
+use snake::level::Level;
+use snake::sim;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+
+const DEFAULT_GAMES: usize = 1000;
+
+/// How one game ended for the bot being measured.
+struct Run {
+    score: u32,
+    ticks: u64,
+    /// Share of the open cells the snake covered at the end.
+    fill: f64,
+    ending: String,
+}
+
+/// Plays one game to the end, or until `max_ticks`, with snake 0 steered
+/// by `controllers[0]` and the rest by the others.
+fn play(
+    config: &Config,
+    level: &Level,
+    seed: u64,
+    controllers: &mut [Box<dyn Controller>],
+    max_ticks: u64,
+) -> Result<Run, String> {
+    let played = sim::play(
+        level,
+        config.difficulty,
+        seed,
+        controllers,
+        max_ticks,
+        false,
+    )?;
+    for (i, controller) in controllers.iter().enumerate() {
+        if let Some(reason) = controller.failure() {
+            eprintln!("snake-sim: snake {} disqualified: {}", i + 1, reason);
+        }
+    }
+    let game = &played.game;
+    let me = &game.players[0];
+    let ending = match (me.death, game.outcome()) {
+        (Some(death), _) => death.to_string(),
+        (None, Some(Outcome::BoardFull)) => "board full".to_string(),
+        (None, Some(Outcome::GoalReached)) | (None, Some(Outcome::Winner(0))) => "won".to_string(),
+        (None, Some(_)) => "beaten".to_string(),
+        (None, None) => "out of time".to_string(),
+    };
+    let open = (level.bounds.cols * level.bounds.rows) as usize - level.walls.len();
+    Ok(Run {
+        score: me.score.val,
+        ticks: played.lasted(0),
+        fill: me.snake.body.len() as f64 / open as f64,
+        ending,
+    })
+}
+
+/// Mean, median and maximum of `values`, which must not be empty.
+fn summary(mut values: Vec<f64>) -> (f64, f64, f64) {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = values.len();
+    let mean = values.iter().sum::<f64>() / n as f64;
+    // The middle value, or the two middle values averaged.
+    let median = (values[(n - 1) / 2] + values[n / 2]) / 2.0;
+    (mean, median, values[n - 1])
+}
+
+/// Pulls `--games` and `--max-ticks` out of `args`, leaving the rest for
+/// `Config::load`.
+fn split_args(args: Vec<String>) -> Result<(usize, Option<u64>, Vec<String>), String> {
+    let mut games = DEFAULT_GAMES;
+    let mut max_ticks = None;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" | "--max-ticks" => {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("missing value for {}", arg))?;
+                let invalid = |_| format!("invalid value {:?} for {}", value, &arg[2..]);
+                if arg == "--games" {
+                    games = value.parse().map_err(invalid)?;
+                } else {
+                    max_ticks = Some(value.parse().map_err(invalid)?);
+                }
+            }
+            _ => rest.push(arg),
+        }
+    }
+    if games == 0 {
+        return Err("games must be at least 1".to_string());
+    }
+    Ok((games, max_ticks, rest))
+}
+
+fn run() -> Result<(), String> {
+    let (games, max_ticks, args) = split_args(env::args().skip(1).collect())?;
+    let config = Config::load(args).map_err(|e| e.to_string())?;
+    if config.campaign {
+        return Err("snake-sim plays one level; pass --level instead of --campaign".to_string());
+    }
+    let level = config.load_level(&resource_path())?;
+    let max_ticks = max_ticks.unwrap_or_else(|| sim::default_max_ticks(&level));
+    let bot = config.bot.as_ref().unwrap_or(&config.ai);
+    let time_limit = Duration::from_millis(config.move_time);
+    let first_seed = config.seed.unwrap_or_else(rand::random);
+
+    let started = Instant::now();
+    let mut runs = Vec::with_capacity(games);
+    for i in 0..games {
+        let mut controllers = vec![bot.controller(time_limit).map_err(|e| e.to_string())?];
+        for _ in 1..config.snakes() {
+            controllers.push(
+                config
+                    .ai
+                    .controller(time_limit)
+                    .map_err(|e| e.to_string())?,
+            );
+        }
+        let seed = first_seed.wrapping_add(i as u64);
+        runs.push(play(&config, &level, seed, &mut controllers, max_ticks)?);
+    }
+    let elapsed = started.elapsed();
+
+    println!(
+        "{} games of {} on a {}x{} board ({}), seeds {} to {}",
+        games,
+        bot,
+        level.bounds.cols,
+        level.bounds.rows,
+        config.difficulty,
+        first_seed,
+        first_seed.wrapping_add(games as u64 - 1)
+    );
+    if config.snakes() > 1 {
+        println!("against {} {} opponent(s)", config.snakes() - 1, config.ai);
+    }
+    println!("{:<8}{:>10}{:>10}{:>10}", "", "mean", "median", "max");
+    let (mean, median, max) = summary(runs.iter().map(|r| f64::from(r.score)).collect());
+    println!("{:<8}{:>10.1}{:>10.1}{:>10}", "score", mean, median, max);
+    let (mean, median, max) = summary(runs.iter().map(|r| r.ticks as f64).collect());
+    println!("{:<8}{:>10.1}{:>10.1}{:>10}", "ticks", mean, median, max);
+    let (mean, median, max) = summary(runs.iter().map(|r| r.fill * 100.0).collect());
+    println!("{:<8}{:>9.1}%{:>9.1}%{:>9.1}%", "fill", mean, median, max);
+
+    let mut endings = BTreeMap::new();
+    for run in &runs {
+        *endings.entry(run.ending.as_str()).or_insert(0) += 1;
+    }
+    let mut endings: Vec<(&str, usize)> = endings.into_iter().collect();
+    endings.sort_by_key(|&(_, count)| Reverse(count));
+    println!("endings:");
+    for (ending, count) in endings {
+        println!(
+            "  {:<14}{:>7}  ({:.1}%)",
+            ending,
+            count,
+            count as f64 * 100.0 / games as f64
+        );
+    }
+    let secs = elapsed.as_secs_f64();
+    println!(
+        "{:.2}s, {:.1} games per second ({:.2} ms per game)",
+        secs,
+        games as f64 / secs.max(1e-9),
+        secs * 1000.0 / games as f64
+    );
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("snake-sim: {}", e);
+        process::exit(2);
+    }
+}
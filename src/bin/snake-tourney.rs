@@ -0,0 +1,212 @@
+//! Runs bots against each other in shared arenas over many rounds and
+//! prints a leaderboard.
+//!
+//! Takes the same `--key value` settings as the game for the board, plus
+//! `--bots` (a comma-separated list of built-in names or `cmd:` commands),
+//! `--rounds`, `--arena`, `--replays` and `--max-ticks`:
+//!
+//!     snake-tourney --bots "greedy,bfs,survival,cmd:python3 bots/example.py" --rounds 50
+
+use snake::config::{resource_path, Config, MAX_SNAKES};
+use snake::external::BotSpec;
+use snake::sim;
+use snake::tournament::{self, Tournament};
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+const DEFAULT_ROUNDS: u64 = 20;
+const DEFAULT_REPLAYS: &str = "replays";
+const PLACES: [&str; 4] = ["1st", "2nd", "3rd", "4th"];
+
+/// Settings for the tournament itself, taken off the command line before
+/// the rest goes to `Config::load`.
+struct Options {
+    bots: Vec<BotSpec>,
+    rounds: u64,
+    arena: Option<usize>,
+    replays: PathBuf,
+    max_ticks: Option<u64>,
+}
+
+fn split_args(args: Vec<String>) -> Result<(Options, Vec<String>), String> {
+    let mut options = Options {
+        bots: Vec::new(),
+        rounds: DEFAULT_ROUNDS,
+        arena: None,
+        replays: PathBuf::from(DEFAULT_REPLAYS),
+        max_ticks: None,
+    };
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let key = match arg.as_str() {
+            "--bots" | "--rounds" | "--arena" | "--replays" | "--max-ticks" => &arg[2..],
+            _ => {
+                rest.push(arg);
+                continue;
+            }
+        };
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for --{}", key))?;
+        let invalid = || format!("invalid value {:?} for {}", value, key);
+        match key {
+            "bots" => {
+                for bot in value.split(',') {
+                    options.bots.push(bot.trim().parse()?);
+                }
+            }
+            "rounds" => options.rounds = value.parse().map_err(|_| invalid())?,
+            "arena" => options.arena = Some(value.parse().map_err(|_| invalid())?),
+            "replays" => options.replays = PathBuf::from(&value),
+            _ => options.max_ticks = Some(value.parse().map_err(|_| invalid())?),
+        }
+    }
+    if options.bots.len() < 2 {
+        return Err("--bots needs at least two bots".to_string());
+    }
+    if options
+        .arena
+        .is_some_and(|size| !(2..=MAX_SNAKES).contains(&size))
+    {
+        return Err(format!(
+            "arena must hold between 2 and {} snakes",
+            MAX_SNAKES
+        ));
+    }
+    Ok((options, rest))
+}
+
+/// A name for each bot, numbered where the same bot is entered twice.
+fn names(bots: &[BotSpec]) -> Vec<String> {
+    bots.iter()
+        .enumerate()
+        .map(|(i, bot)| {
+            let name = bot.to_string();
+            let copy = bots[..i].iter().filter(|&other| other == bot).count();
+            if bots.iter().filter(|&other| other == bot).count() > 1 {
+                format!("{} #{}", name, copy + 1)
+            } else {
+                name
+            }
+        })
+        .collect()
+}
+
+fn leaderboard(tournament: &Tournament) -> String {
+    let mut text = String::new();
+    writeln!(
+        text,
+        "{:>4}  {:<28}{:>8}{:>7}{:>6}{:>8}{:>8}{:>5}",
+        "", "bot", "rating", "games", "wins", "win %", "score", "dq"
+    )
+    .unwrap();
+    for (rank, i) in tournament.leaderboard().into_iter().enumerate() {
+        let s = &tournament.standings()[i];
+        writeln!(
+            text,
+            "{:>4}  {:<28}{:>8.0}{:>7}{:>6}{:>7.1}%{:>8.1}{:>5}",
+            rank + 1,
+            s.name,
+            s.rating,
+            s.games,
+            s.wins,
+            s.win_rate() * 100.0,
+            s.mean_score(),
+            s.disqualified
+        )
+        .unwrap();
+    }
+    text
+}
+
+fn run() -> Result<(), String> {
+    let (options, args) = split_args(env::args().skip(1).collect())?;
+    let config = Config::load(args).map_err(|e| e.to_string())?;
+    let level = config.load_level(&resource_path())?;
+    let arena = options
+        .arena
+        .unwrap_or_else(|| options.bots.len().min(MAX_SNAKES));
+    let max_ticks = options
+        .max_ticks
+        .unwrap_or_else(|| sim::default_max_ticks(&level));
+    let time_limit = Duration::from_millis(config.move_time);
+    let first_seed = config.seed.unwrap_or_else(rand::random);
+    let replays = &options.replays;
+    fs::create_dir_all(replays).map_err(|e| format!("{}: {}", replays.display(), e))?;
+
+    let names = names(&options.bots);
+    let mut tournament = Tournament::new(names.iter().cloned());
+    let mut index = String::new();
+    let mut seed = first_seed;
+    for round in 1..=options.rounds {
+        let arenas = tournament::arenas(options.bots.len(), arena, seed);
+        for (a, entrants) in arenas.iter().enumerate() {
+            let mut controllers = Vec::with_capacity(entrants.len());
+            for &bot in entrants {
+                let controller = options.bots[bot]
+                    .controller(time_limit)
+                    .map_err(|e| format!("{}: {}", names[bot], e))?;
+                controllers.push(controller);
+            }
+            let played = tournament::play_arena(
+                &level,
+                config.difficulty,
+                seed,
+                &mut controllers,
+                max_ticks,
+            )?;
+            tournament.record(entrants, &played.finishes);
+
+            let file = format!("round-{:03}-arena-{}.txt", round, a + 1);
+            let path = replays.join(&file);
+            fs::write(&path, played.replay.to_text())
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let results: Vec<String> = entrants
+                .iter()
+                .zip(&played.finishes)
+                .map(|(&bot, finish)| {
+                    format!(
+                        "{} {} ({}{})",
+                        names[bot],
+                        PLACES[finish.place.min(PLACES.len() - 1)],
+                        finish.score,
+                        if finish.disqualified {
+                            ", disqualified"
+                        } else {
+                            ""
+                        }
+                    )
+                })
+                .collect();
+            writeln!(index, "{}\tseed {}\t{}", file, seed, results.join(", ")).unwrap();
+            seed = seed.wrapping_add(1);
+        }
+    }
+
+    let table = leaderboard(&tournament);
+    print!("{}", table);
+    for (file, text) in &[("index.txt", &index), ("leaderboard.txt", &table)] {
+        let path = replays.join(file);
+        fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    println!(
+        "{} rounds of {}-snake arenas, seeds from {}; replays in {}",
+        options.rounds,
+        arena,
+        first_seed,
+        replays.display()
+    );
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("snake-tourney: {}", e);
+        process::exit(2);
+    }
+}
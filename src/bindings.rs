@@ -0,0 +1,260 @@
+use crate::game::Direction;
+use std::fmt;
+use std::str::FromStr;
+
+/// Something a key or gamepad button can be bound to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Steer(Direction),
+    /// Turn a quarter turn from wherever the snake is heading.
+    TurnLeft,
+    TurnRight,
+    Pause,
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Action, String> {
+        match s {
+            "turn-left" => Ok(Action::TurnLeft),
+            "turn-right" => Ok(Action::TurnRight),
+            "pause" => Ok(Action::Pause),
+            _ => s
+                .parse()
+                .map(Action::Steer)
+                .map_err(|_| format!("unknown action {:?}", s)),
+        }
+    }
+}
+
+/// How steering keys are read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ControlMode {
+    /// Each direction key sends the snake that way on the board.
+    Absolute,
+    /// Left and right turn the snake from its own point of view; up and
+    /// down do nothing.
+    Relative,
+}
+
+impl FromStr for ControlMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ControlMode, String> {
+        match s {
+            "absolute" => Ok(ControlMode::Absolute),
+            "relative" => Ok(ControlMode::Relative),
+            _ => Err(format!("expected absolute or relative, found {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ControlMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ControlMode::Absolute => "Absolute",
+            ControlMode::Relative => "Relative",
+        })
+    }
+}
+
+impl ControlMode {
+    pub fn toggle(self) -> ControlMode {
+        match self {
+            ControlMode::Absolute => ControlMode::Relative,
+            ControlMode::Relative => ControlMode::Absolute,
+        }
+    }
+    /// What a bound action means in this mode, or `None` if it does
+    /// nothing.
+    pub fn interpret(self, action: Action) -> Option<Action> {
+        match (self, action) {
+            (ControlMode::Relative, Action::Steer(Direction::Left)) => Some(Action::TurnLeft),
+            (ControlMode::Relative, Action::Steer(Direction::Right)) => Some(Action::TurnRight),
+            (ControlMode::Relative, Action::Steer(_)) => None,
+            _ => Some(action),
+        }
+    }
+}
+
+/// Key schemes for `keys = <scheme>, ...`, by name.
+const SCHEMES: &[(&str, &[(&str, Action)])] = &[
+    (
+        "arrows",
+        &[
+            ("up", Action::Steer(Direction::Up)),
+            ("down", Action::Steer(Direction::Down)),
+            ("left", Action::Steer(Direction::Left)),
+            ("right", Action::Steer(Direction::Right)),
+            ("p", Action::Pause),
+        ],
+    ),
+    (
+        "wasd",
+        &[
+            ("w", Action::Steer(Direction::Up)),
+            ("s", Action::Steer(Direction::Down)),
+            ("a", Action::Steer(Direction::Left)),
+            ("d", Action::Steer(Direction::Right)),
+            ("p", Action::Pause),
+        ],
+    ),
+    (
+        "hjkl",
+        &[
+            ("k", Action::Steer(Direction::Up)),
+            ("j", Action::Steer(Direction::Down)),
+            ("h", Action::Steer(Direction::Left)),
+            ("l", Action::Steer(Direction::Right)),
+            ("p", Action::Pause),
+        ],
+    ),
+    (
+        "turns",
+        &[
+            ("z", Action::TurnLeft),
+            ("x", Action::TurnRight),
+            ("p", Action::Pause),
+        ],
+    ),
+    (
+        "pad",
+        &[
+            ("pad:dpup", Action::Steer(Direction::Up)),
+            ("pad:dpdown", Action::Steer(Direction::Down)),
+            ("pad:dpleft", Action::Steer(Direction::Left)),
+            ("pad:dpright", Action::Steer(Direction::Right)),
+            ("pad:leftshoulder", Action::TurnLeft),
+            ("pad:rightshoulder", Action::TurnRight),
+            ("pad:start", Action::Pause),
+        ],
+    ),
+];
+
+/// Which action each key or button triggers.
+///
+/// Keys are named as SDL names them (`Up`, `W`, `Space`, ...) and gamepad
+/// buttons as `pad:` followed by their SDL mapping name (`pad:dpup`,
+/// `pad:a`, `pad:start`, ...). Names are not case sensitive.
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: Vec<(String, Action)>,
+}
+
+impl Default for Bindings {
+    /// Arrow keys, WASD and the gamepad.
+    fn default() -> Bindings {
+        let mut bindings = Bindings::new();
+        for scheme in &["arrows", "wasd", "pad"] {
+            bindings.add_scheme(scheme).unwrap();
+        }
+        bindings
+    }
+}
+
+impl Bindings {
+    /// No bindings at all.
+    pub fn new() -> Bindings {
+        Bindings { keys: Vec::new() }
+    }
+    /// Adds every binding of the scheme called `name`: `arrows`, `wasd`,
+    /// `hjkl`, `turns` (Z and X to turn) or `pad`.
+    pub fn add_scheme(&mut self, name: &str) -> Result<(), String> {
+        let scheme = SCHEMES
+            .iter()
+            .find(|(scheme, _)| *scheme == name)
+            .ok_or_else(|| format!("unknown key scheme {:?}", name))?;
+        for &(key, action) in scheme.1 {
+            self.bind(key, action);
+        }
+        Ok(())
+    }
+    /// Makes `key` trigger `action`, replacing whatever it did before.
+    pub fn bind(&mut self, key: &str, action: Action) {
+        let key = key.to_lowercase();
+        self.keys.retain(|(k, _)| *k != key);
+        self.keys.push((key, action));
+    }
+    /// Removes every key bound to `action`.
+    pub fn unbind(&mut self, action: Action) {
+        self.keys.retain(|&(_, a)| a != action);
+    }
+    pub fn action(&self, key: &str) -> Option<Action> {
+        let key = key.to_lowercase();
+        self.keys.iter().find(|(k, _)| *k == key).map(|&(_, a)| a)
+    }
+    /// The keys bound to `action`, in the order they were bound.
+    pub fn keys_for(&self, action: Action) -> Vec<&str> {
+        self.keys
+            .iter()
+            .filter(|&&(_, a)| a == action)
+            .map(|(k, _)| k.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemes_bind_their_keys() {
+        let mut bindings = Bindings::new();
+        bindings.add_scheme("hjkl").unwrap();
+        bindings.add_scheme("turns").unwrap();
+        assert_eq!(bindings.action("K"), Some(Action::Steer(Direction::Up)));
+        assert_eq!(bindings.action("x"), Some(Action::TurnRight));
+        assert_eq!(bindings.action("up"), None);
+        assert_eq!(bindings.keys_for(Action::Pause), vec!["p"]);
+        assert!(bindings.add_scheme("dvorak").is_err());
+
+        let defaults = Bindings::default();
+        assert_eq!(
+            defaults.keys_for(Action::Steer(Direction::Left)),
+            vec!["left", "a", "pad:dpleft"]
+        );
+        assert_eq!(defaults.action("pad:start"), Some(Action::Pause));
+    }
+
+    #[test]
+    fn rebinding_replaces_the_old_binding() {
+        let mut bindings = Bindings::default();
+        bindings.bind("A", Action::TurnLeft);
+        assert_eq!(bindings.action("a"), Some(Action::TurnLeft));
+        assert_eq!(
+            bindings.keys_for(Action::Steer(Direction::Left)),
+            vec!["left", "pad:dpleft"]
+        );
+        bindings.unbind(Action::Pause);
+        bindings.bind("space", Action::Pause);
+        assert_eq!(bindings.keys_for(Action::Pause), vec!["space"]);
+        assert_eq!(bindings.action("p"), None);
+    }
+
+    #[test]
+    fn actions_parse_from_their_names() {
+        assert_eq!("turn-left".parse(), Ok(Action::TurnLeft));
+        assert_eq!("down".parse(), Ok(Action::Steer(Direction::Down)));
+        assert!("jump".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn relative_mode_turns_left_and_right_and_drops_up_and_down() {
+        let relative = ControlMode::Relative;
+        assert_eq!(
+            relative.interpret(Action::Steer(Direction::Left)),
+            Some(Action::TurnLeft)
+        );
+        assert_eq!(
+            relative.interpret(Action::Steer(Direction::Right)),
+            Some(Action::TurnRight)
+        );
+        assert_eq!(relative.interpret(Action::Steer(Direction::Up)), None);
+        assert_eq!(relative.interpret(Action::Pause), Some(Action::Pause));
+        let absolute = relative.toggle();
+        assert_eq!(absolute, ControlMode::Absolute);
+        let up = Action::Steer(Direction::Up);
+        assert_eq!(absolute.interpret(up), Some(up));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::level::Level;
+
+/// An ordered run of levels, each of which has to be cleared to unlock the
+/// next one.
+pub struct Campaign {
+    levels: Vec<Level>,
+    current: usize,
+    unlocked: usize,
+}
+
+/// Level paths listed in a campaign file, one per line. Blank lines and
+/// lines starting with `#` are skipped.
+pub fn parse_list(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The furthest unlocked level saved in a progress file, or the first level
+/// if it holds anything else.
+pub fn parse_progress(text: &str) -> usize {
+    text.trim().parse().unwrap_or(0)
+}
+
+pub fn progress_text(unlocked: usize) -> String {
+    format!("{}\n", unlocked)
+}
+
+impl Campaign {
+    /// Starts at the furthest unlocked level. `levels` must not be empty.
+    pub fn new(levels: Vec<Level>, unlocked: usize) -> Campaign {
+        assert!(!levels.is_empty(), "a campaign needs at least one level");
+        let unlocked = unlocked.min(levels.len() - 1);
+        Campaign {
+            levels,
+            current: unlocked,
+            unlocked,
+        }
+    }
+    pub fn level(&self) -> &Level {
+        &self.levels[self.current]
+    }
+    /// Index of the level being played, from 0.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+    /// Index of the furthest level the player may start from.
+    pub fn unlocked(&self) -> usize {
+        self.unlocked
+    }
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+    pub fn is_last(&self) -> bool {
+        self.current + 1 == self.levels.len()
+    }
+    /// Picks another unlocked level to play, `offset` levels away.
+    pub fn select(&mut self, offset: isize) {
+        let target = self.current as isize + offset;
+        self.current = target.max(0).min(self.unlocked as isize) as usize;
+    }
+    /// Records the current level as cleared and moves on to the next one.
+    /// Returns `false` when it was the last level.
+    pub fn advance(&mut self) -> bool {
+        if self.is_last() {
+            return false;
+        }
+        self.current += 1;
+        self.unlocked = self.unlocked.max(self.current);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Bounds;
+
+    fn campaign(levels: usize, unlocked: usize) -> Campaign {
+        let levels = (0..levels)
+            .map(|n| {
+                let mut level = Level::empty(Bounds::new(8, 8, false));
+                level.name = format!("level {}", n + 1);
+                level
+            })
+            .collect();
+        Campaign::new(levels, unlocked)
+    }
+
+    #[test]
+    fn starts_at_the_furthest_unlocked_level() {
+        assert_eq!(campaign(3, 1).level().name, "level 2");
+        let past_the_end = campaign(3, 9);
+        assert_eq!((past_the_end.current(), past_the_end.unlocked()), (2, 2));
+    }
+
+    #[test]
+    fn select_stays_within_unlocked_levels() {
+        let mut campaign = campaign(4, 2);
+        campaign.select(-1);
+        assert_eq!(campaign.current(), 1);
+        campaign.select(-5);
+        assert_eq!(campaign.current(), 0);
+        campaign.select(3);
+        assert_eq!(campaign.current(), 2);
+    }
+
+    #[test]
+    fn advance_unlocks_levels_and_stops_at_the_last() {
+        let mut campaign = campaign(3, 0);
+        assert!(campaign.advance());
+        assert!(campaign.advance());
+        assert!(campaign.is_last());
+        assert!(!campaign.advance());
+        assert_eq!((campaign.current(), campaign.unlocked()), (2, 2));
+        // Replaying an earlier level does not lose what is unlocked.
+        campaign.select(-2);
+        assert!(campaign.advance());
+        assert_eq!((campaign.current(), campaign.unlocked()), (1, 2));
+    }
+
+    #[test]
+    fn progress_round_trips() {
+        assert_eq!(parse_progress(&progress_text(5)), 5);
+        assert_eq!(parse_progress(""), 0);
+        assert_eq!(parse_progress("level three\n"), 0);
+    }
+
+    #[test]
+    fn list_skips_blanks_and_comments() {
+        let text = "# the campaign\nlevels/one.txt\n\n  levels/two.txt  \n";
+        assert_eq!(parse_list(text), vec!["levels/one.txt", "levels/two.txt"]);
+    }
+}
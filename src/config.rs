@@ -0,0 +1,441 @@
+use crate::ai::AiKind;
+use crate::bindings::{Action, Bindings, ControlMode};
+use crate::difficulty::Difficulty;
+use crate::external::BotSpec;
+use crate::game::Bounds;
+use crate::level::{BuiltinLevel, Level};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Read from the working directory when no `--config` is given.
+pub const DEFAULT_CONFIG_FILE: &str = "snake.conf";
+pub const MIN_BOARD_SIDE: i32 = 4;
+/// Keeps cell counts well inside `i32`.
+pub const MAX_BOARD_SIDE: i32 = 1000;
+/// Most people that can share the keyboard in versus mode.
+pub const MAX_PLAYERS: usize = 2;
+/// Most snakes on one board, people and computer opponents together.
+pub const MAX_SNAKES: usize = 4;
+
+/// Runtime settings for a game.
+///
+/// Values start at their defaults, are overridden by `key = value` lines in
+/// the config file, then by `--key value` flags on the command line.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub cols: i32,
+    pub rows: i32,
+    /// Width and height of one cell, in pixels.
+    pub cell_size: u32,
+    pub seed: Option<u64>,
+    /// Leaving the board re-enters from the opposite edge.
+    pub wrap: bool,
+    pub layout: BuiltinLevel,
+    /// A level file inside the resources directory, used instead of
+    /// `layout`.
+    pub level: Option<String>,
+    /// Play the levels listed in `levels/campaign.txt` in order.
+    pub campaign: bool,
+    pub difficulty: Difficulty,
+    pub bindings: Bindings,
+    /// Player two's keys in versus mode. They win over player one's when
+    /// both bind the same key.
+    pub bindings2: Bindings,
+    pub controls: ControlMode,
+    /// 2 for a head-to-head game on one keyboard.
+    pub players: usize,
+    /// Rounds a player needs to win a versus match.
+    pub rounds: u32,
+    /// Computer-controlled snakes added after the players.
+    pub opponents: usize,
+    pub ai: BotSpec,
+    /// Lets a bot steer player one's snake.
+    pub bot: Option<BotSpec>,
+    /// Milliseconds an external bot has to answer each tick.
+    pub move_time: u64,
+    /// A replay file to watch instead of playing.
+    pub replay: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            cols: 80,
+            rows: 60,
+            cell_size: 10,
+            seed: None,
+            wrap: false,
+            layout: BuiltinLevel::Empty,
+            level: None,
+            campaign: false,
+            difficulty: Difficulty::Normal,
+            bindings: Bindings::default(),
+            bindings2: default_bindings2(),
+            controls: ControlMode::Absolute,
+            players: 1,
+            rounds: 3,
+            opponents: 0,
+            ai: BotSpec::Builtin(AiKind::Pathfinder),
+            bot: None,
+            move_time: 100,
+            replay: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for ConfigError {}
+
+/// The directory holding levels, sounds and fonts: the crate's own when run
+/// through cargo, otherwise `resources` under the working directory.
+pub fn resource_path() -> PathBuf {
+    match env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => Path::new(&manifest_dir).join("resources"),
+        Err(_) => PathBuf::from("resources"),
+    }
+}
+
+fn default_bindings2() -> Bindings {
+    let mut bindings = Bindings::new();
+    bindings.add_scheme("arrows").unwrap();
+    bindings
+}
+
+/// Handles `keys` and `key-<action>`, with `prefix` already stripped.
+fn set_binding(bindings: &mut Bindings, key: &str, value: &str) -> Result<bool, ConfigError> {
+    if key == "keys" {
+        let mut fresh = Bindings::new();
+        for scheme in value.split(',') {
+            fresh.add_scheme(scheme.trim()).map_err(ConfigError)?;
+        }
+        *bindings = fresh;
+    } else if let Some(action) = key.strip_prefix("key-") {
+        let action: Action = action.parse().map_err(ConfigError)?;
+        bindings.unbind(action);
+        for name in value.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            bindings.bind(name, action);
+        }
+    } else {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+    value
+        .parse()
+        .map_err(|_| ConfigError(format!("invalid value {:?} for {}", value, key)))
+}
+
+impl Config {
+    /// Builds a config from command line arguments, not including the
+    /// program name.
+    pub fn load<I: IntoIterator<Item = String>>(args: I) -> Result<Config, ConfigError> {
+        let mut flags = Vec::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let key = match arg.strip_prefix("--") {
+                Some(key) => key.to_string(),
+                None => return Err(ConfigError(format!("unexpected argument {:?}", arg))),
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| ConfigError(format!("missing value for --{}", key)))?;
+            flags.push((key, value));
+        }
+
+        let mut config = Config::default();
+        match flags.iter().find(|(key, _)| key == "config") {
+            Some((_, path)) => config.apply_file(Path::new(path))?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                config.apply_file(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => {}
+        }
+        for (key, value) in &flags {
+            if key != "config" {
+                config.set(key, value)?;
+            }
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Applies every `key = value` line of the file at `path`. Blank lines
+    /// and lines starting with `#` are skipped.
+    pub fn apply_file(&mut self, path: &Path) -> Result<(), ConfigError> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| ConfigError(format!("{}: {}", path.display(), e)))?;
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = match line.split_once('=') {
+                Some((key, value)) => self.set(key.trim(), value.trim()),
+                None => Err(ConfigError("expected `key = value`".to_string())),
+            };
+            result.map_err(|e| ConfigError(format!("{}:{}: {}", path.display(), n + 1, e)))?;
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        match key {
+            "cols" => self.cols = parse(key, value)?,
+            "rows" => self.rows = parse(key, value)?,
+            "cell-size" => self.cell_size = parse(key, value)?,
+            "seed" => self.seed = Some(parse(key, value)?),
+            "wrap" => self.wrap = parse(key, value)?,
+            "layout" => self.layout = value.parse().map_err(ConfigError)?,
+            "level" => self.level = Some(value.to_string()),
+            "campaign" => self.campaign = parse(key, value)?,
+            "difficulty" => self.difficulty = value.parse().map_err(ConfigError)?,
+            "controls" => self.controls = value.parse().map_err(ConfigError)?,
+            "players" => self.players = parse(key, value)?,
+            "rounds" => self.rounds = parse(key, value)?,
+            "opponents" => self.opponents = parse(key, value)?,
+            "ai" => self.ai = value.parse().map_err(ConfigError)?,
+            "bot" => self.bot = Some(value.parse().map_err(ConfigError)?),
+            "move-time" => self.move_time = parse(key, value)?,
+            "replay" => self.replay = Some(value.to_string()),
+            _ if set_binding(&mut self.bindings, key, value)? => {}
+            _ if key.starts_with("p2-")
+                && set_binding(&mut self.bindings2, &key["p2-".len()..], value)? => {}
+            _ => return Err(ConfigError(format!("unknown setting {:?}", key))),
+        }
+        Ok(())
+    }
+
+    /// The board to play without the game window: the `level` file, read
+    /// from under `resources`, or else the built-in layout.
+    pub fn load_level(&self, resources: &Path) -> Result<Level, String> {
+        let mut level = match self.level {
+            Some(ref path) => {
+                let path = resources.join(path.trim_start_matches('/'));
+                let text =
+                    fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                Level::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?
+            }
+            None => self.layout.build(Bounds::new(self.cols, self.rows, false)),
+        };
+        level.bounds.wrap = self.wrap;
+        Ok(level)
+    }
+    /// Players and computer opponents together.
+    pub fn snakes(&self) -> usize {
+        self.players + self.opponents
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let sides = MIN_BOARD_SIDE..=MAX_BOARD_SIDE;
+        if !sides.contains(&self.cols) || !sides.contains(&self.rows) {
+            return Err(ConfigError(format!(
+                "board must be between {0}x{0} and {1}x{1} cells",
+                MIN_BOARD_SIDE, MAX_BOARD_SIDE
+            )));
+        }
+        if self.players < 1 || self.players > MAX_PLAYERS {
+            return Err(ConfigError(format!(
+                "players must be between 1 and {}",
+                MAX_PLAYERS
+            )));
+        }
+        if self.players + self.opponents > MAX_SNAKES {
+            return Err(ConfigError(format!(
+                "at most {} snakes fit on a board",
+                MAX_SNAKES
+            )));
+        }
+        if self.snakes() > 1 && self.campaign {
+            return Err(ConfigError(
+                "the campaign is for one player only".to_string(),
+            ));
+        }
+        if self.move_time == 0 {
+            return Err(ConfigError(
+                "move-time must be at least 1 millisecond".to_string(),
+            ));
+        }
+        if self.cell_size < 2 {
+            return Err(ConfigError(
+                "cell-size must be at least 2 pixels".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Direction;
+
+    fn load(args: &[&str]) -> Result<Config, ConfigError> {
+        Config::load(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Writes `text` to a config file of its own and loads it, followed by
+    /// the flags in `args`.
+    fn load_file(name: &str, text: &str, args: &[&str]) -> Result<Config, ConfigError> {
+        let path = env::temp_dir().join(format!("snake-test-{}-{}", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let mut all = vec!["--config", path.to_str().unwrap()];
+        all.extend_from_slice(args);
+        let config = load(&all);
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn flags_override_defaults() {
+        let config = load(&[
+            "--cols",
+            "20",
+            "--rows",
+            "15",
+            "--cell-size",
+            "24",
+            "--seed",
+            "7",
+        ])
+        .unwrap();
+        assert_eq!((config.cols, config.rows), (20, 15));
+        assert_eq!(config.cell_size, 24);
+        assert_eq!(config.seed, Some(7));
+    }
+
+    #[test]
+    fn bad_arguments_are_errors() {
+        assert!(load(&["cols", "20"]).is_err());
+        assert!(load(&["--cols"]).is_err());
+        assert!(load(&["--cols", "wide"]).is_err());
+        assert!(load(&["--colour", "red"]).is_err());
+    }
+
+    #[test]
+    fn board_and_cell_sizes_are_validated() {
+        assert!(load(&["--cols", "3"]).is_err());
+        assert!(load(&["--rows", "1001"]).is_err());
+        assert!(load(&["--cols", "100000", "--rows", "100000"]).is_err());
+        assert!(load(&["--cols", "4", "--rows", "1000"]).is_ok());
+        assert!(load(&["--cell-size", "1"]).is_err());
+    }
+
+    #[test]
+    fn file_settings_are_overridden_by_flags() {
+        let text = "# comment\n\ncols = 30\nrows = 25\n";
+        let config = load_file("flags.conf", text, &["--rows", "12"]).unwrap();
+        assert_eq!((config.cols, config.rows), (30, 12));
+    }
+
+    #[test]
+    fn file_errors_give_the_line() {
+        let e = load_file("bad.conf", "cols = 30\n\nrows\n", &[]).unwrap_err();
+        assert!(e.0.ends_with(":3: expected `key = value`"), "{}", e);
+        let e = load_file("bad2.conf", "cols = 30\nrows = tall\n", &[]).unwrap_err();
+        assert!(e.0.contains(":2: "), "{}", e);
+    }
+
+    #[test]
+    fn the_campaign_is_for_one_snake() {
+        assert!(load(&["--campaign", "true"]).is_ok());
+        assert!(load(&["--players", "2", "--campaign", "true"]).is_err());
+    }
+
+    #[test]
+    fn file_sets_key_bindings() {
+        let text = "keys = hjkl\nkey-pause = space, q\n";
+        let config = load_file("keys.conf", text, &[]).unwrap();
+        assert_eq!(
+            config.bindings.action("k"),
+            Some(Action::Steer(Direction::Up))
+        );
+        assert_eq!(config.bindings.action("up"), None);
+        assert_eq!(config.bindings.keys_for(Action::Pause), vec!["space", "q"]);
+        let e = load_file("keys2.conf", "keys = arrows, dvorak\n", &[]).unwrap_err();
+        assert!(e.0.contains(":1: "), "{}", e);
+    }
+
+    #[test]
+    fn players_are_limited() {
+        assert!(load(&["--players", "2"]).is_ok());
+        assert!(load(&["--players", "0"]).is_err());
+        assert!(load(&["--players", "3"]).is_err());
+    }
+
+    #[test]
+    fn file_sets_player_two_keys() {
+        let config = load_file(
+            "p2.conf",
+            "p2-key-up = i
+",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            config.bindings2.action("i"),
+            Some(Action::Steer(Direction::Up))
+        );
+        assert_eq!(config.bindings2.action("up"), None);
+        assert_eq!(
+            config.bindings.action("up"),
+            Some(Action::Steer(Direction::Up))
+        );
+    }
+
+    #[test]
+    fn opponents_share_the_board() {
+        let config = load(&["--opponents", "2", "--ai", "greedy"]).unwrap();
+        assert_eq!(config.snakes(), 3);
+        assert_eq!(config.ai, BotSpec::Builtin(AiKind::Greedy));
+        assert!(load(&["--players", "2", "--opponents", "2"]).is_ok());
+        assert!(load(&["--players", "2", "--opponents", "3"]).is_err());
+        assert!(load(&["--ai", "clever"]).is_err());
+    }
+
+    #[test]
+    fn bot_takes_a_built_in_name() {
+        let config = load(&["--bot", "hamilton"]).unwrap();
+        assert_eq!(config.bot, Some(BotSpec::Builtin(AiKind::Hamiltonian)));
+        assert_eq!(load(&[]).unwrap().bot, None);
+        assert!(load(&["--bot", "clever"]).is_err());
+    }
+
+    #[test]
+    fn bots_can_be_commands_with_a_time_limit() {
+        let config = load(&["--ai", "cmd:python3 bot.py", "--move-time", "50"]).unwrap();
+        assert_eq!(config.ai, BotSpec::External("python3 bot.py".to_string()));
+        assert_eq!(config.move_time, 50);
+        assert!(load(&["--move-time", "0"]).is_err());
+        assert!(load(&["--bot", "cmd:"]).is_err());
+    }
+
+    #[test]
+    fn wrap_is_a_boolean_setting() {
+        assert!(load(&["--wrap", "true"]).unwrap().wrap);
+        assert!(!load(&[]).unwrap().wrap);
+        assert!(load(&["--wrap", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn layout_picks_a_built_in_level() {
+        assert_eq!(
+            load(&["--layout", "box"]).unwrap().layout,
+            BuiltinLevel::Box
+        );
+        assert_eq!(load(&[]).unwrap().layout, BuiltinLevel::Empty);
+        assert!(load(&["--layout", "maze"]).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use crate::game::{Bounds, Direction, Game, GridPos, Player, Snake};
+
+/// A read-only view of the board for one snake, handed to its `Controller`
+/// once per tick.
+pub struct Snapshot<'a> {
+    pub bounds: Bounds,
+    pub walls: &'a [GridPos],
+    pub apple: GridPos,
+    pub players: &'a [Player],
+    /// Which of `players` is being steered.
+    pub me: usize,
+    /// Ticks played so far.
+    pub tick: u64,
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(game: &'a Game, me: usize) -> Snapshot<'a> {
+        Snapshot {
+            bounds: game.bounds,
+            walls: &game.walls,
+            apple: game.apple.pos,
+            players: &game.players,
+            me,
+            tick: game.ticks(),
+        }
+    }
+    /// The snake being steered.
+    pub fn snake(&self) -> &'a Snake {
+        &self.players[self.me].snake
+    }
+}
+
+/// Anything that can steer a snake: the keyboard, a built-in AI or a bot.
+pub trait Controller {
+    /// The direction to turn this tick, or `None` to carry straight on.
+    /// Reversals are ignored just as they are for the keyboard.
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction>;
+    /// Why this controller can no longer steer, once it has broken down or
+    /// broken the rules. Its snake should then be disqualified.
+    fn failure(&self) -> Option<&str> {
+        None
+    }
+}
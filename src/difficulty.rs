@@ -0,0 +1,131 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A named speed curve: how fast a game starts, how much quicker each apple
+/// makes it and how fast it can get.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Insane,
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            "insane" => Ok(Difficulty::Insane),
+            _ => Err(format!("unknown difficulty {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Insane => "Insane",
+        })
+    }
+}
+
+impl Difficulty {
+    /// Milliseconds per tick at a score of zero.
+    pub fn start(self) -> u64 {
+        match self {
+            Difficulty::Easy => 150,
+            Difficulty::Normal => 125,
+            Difficulty::Hard => 100,
+            Difficulty::Insane => 70,
+        }
+    }
+    /// Milliseconds taken off the tick for every point scored.
+    pub fn acceleration(self) -> u64 {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Normal => 2,
+            Difficulty::Hard => 3,
+            Difficulty::Insane => 4,
+        }
+    }
+    /// The shortest tick the curve goes down to.
+    pub fn floor(self) -> u64 {
+        match self {
+            Difficulty::Easy => 90,
+            Difficulty::Normal => 60,
+            Difficulty::Hard => 45,
+            Difficulty::Insane => 30,
+        }
+    }
+    /// Milliseconds per tick at `score`, starting from `start` instead of
+    /// the preset's own start speed. A start already below the floor stays
+    /// where it is.
+    pub fn interval(self, start: u64, score: u32) -> u64 {
+        let floor = self.floor().min(start);
+        start
+            .saturating_sub(self.acceleration() * u64::from(score))
+            .max(floor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Insane,
+    ];
+
+    #[test]
+    fn each_apple_takes_the_acceleration_off() {
+        assert_eq!(Difficulty::Normal.interval(125, 0), 125);
+        assert_eq!(Difficulty::Normal.interval(125, 10), 105);
+        assert_eq!(Difficulty::Insane.interval(100, 5), 80);
+        for &difficulty in &ALL {
+            let start = difficulty.start();
+            let curve: Vec<u64> = (0..100)
+                .map(|score| difficulty.interval(start, score))
+                .collect();
+            assert!(
+                curve.windows(2).all(|pair| pair[1] <= pair[0]),
+                "{}",
+                difficulty
+            );
+        }
+    }
+
+    #[test]
+    fn the_interval_never_drops_below_the_floor() {
+        for &difficulty in &ALL {
+            let floor = difficulty.floor();
+            assert!(floor <= difficulty.start());
+            assert_eq!(difficulty.interval(difficulty.start(), 1_000), floor);
+            assert_eq!(difficulty.interval(difficulty.start(), u32::MAX), floor);
+        }
+        // A level that starts faster than the floor keeps its own speed.
+        assert_eq!(Difficulty::Easy.interval(50, 0), 50);
+        assert_eq!(Difficulty::Easy.interval(50, 30), 50);
+    }
+
+    #[test]
+    fn harder_presets_are_faster() {
+        for pair in ALL.windows(2) {
+            assert!(pair[1].start() < pair[0].start());
+            assert!(pair[1].floor() < pair[0].floor());
+        }
+        for &difficulty in &ALL {
+            let name = difficulty.to_string().to_lowercase();
+            assert_eq!(name.parse(), Ok(difficulty));
+        }
+    }
+}
@@ -0,0 +1,528 @@
+//! A reinforcement-learning style interface over `Game`: reset to a seed,
+//! then step one move at a time, getting back an observation, a reward and
+//! whether the episode is over.
+
+use crate::ai::{manhattan, AiKind};
+use crate::controller::{Controller, Snapshot};
+use crate::difficulty::Difficulty;
+use crate::game::{Bounds, Death, Direction, Game, GridPos, Outcome};
+use crate::level::Level;
+
+/// What the agent can do each tick, relative to where it is heading, so
+/// there is no way to ask for a reversal.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Move {
+    Straight,
+    Left,
+    Right,
+}
+
+impl Move {
+    /// Every move, in the order `from_index` numbers them.
+    pub const ALL: [Move; 3] = [Move::Straight, Move::Left, Move::Right];
+
+    /// The move numbered `i`, for agents that pick from a discrete range.
+    pub fn from_index(i: usize) -> Option<Move> {
+        Move::ALL.get(i).copied()
+    }
+    fn direction(self, heading: Direction) -> Direction {
+        match self {
+            Move::Straight => heading,
+            Move::Left => heading.turn_left(),
+            Move::Right => heading.turn_right(),
+        }
+    }
+}
+
+/// How the board is turned into numbers for the agent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// One plane per feature over the whole board, shaped
+    /// `[GRID_CHANNELS, rows, cols]`: walls, the agent's body, its head,
+    /// other snakes and the apple, each cell 1.0 or 0.0.
+    Grid,
+    /// Looking out from the head in the eight compass directions, starting
+    /// north and going clockwise, three values per ray: how close the
+    /// nearest wall or edge is, the nearest snake body, and the apple, each
+    /// as 1/distance or 0.0 if the ray never meets one. Shaped `[8, 3]`.
+    Rays,
+    /// The apple's offset from the head in the snake's own frame, as
+    /// (ahead, to the right) divided by the board's size, then 1.0 or 0.0
+    /// for whether moving straight, left or right would crash. Shaped `[5]`.
+    Apple,
+}
+
+pub const GRID_CHANNELS: usize = 5;
+
+/// The eight ray directions for `Encoding::Rays`, as (columns, rows) per
+/// step.
+const RAYS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+/// A flat buffer of values in row-major order, with its shape.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    pub data: Vec<f32>,
+    pub shape: Vec<usize>,
+}
+
+/// The reward for each kind of thing that can happen in a tick. They add
+/// up when several happen at once.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rewards {
+    pub apple: f32,
+    pub death: f32,
+    /// Clearing the level's goal, filling the board or outliving every
+    /// opponent.
+    pub win: f32,
+    /// Given every tick the agent survives; a small negative value pushes
+    /// it to hurry.
+    pub step: f32,
+    /// Given per cell the head moves towards the apple, and taken away per
+    /// cell it moves away.
+    pub approach: f32,
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards {
+            apple: 1.0,
+            death: -1.0,
+            win: 1.0,
+            step: 0.0,
+            approach: 0.0,
+        }
+    }
+}
+
+/// Everything about an environment that stays the same across episodes.
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    pub level: Level,
+    pub difficulty: Difficulty,
+    pub encoding: Encoding,
+    pub rewards: Rewards,
+    /// Ends the episode early after this many ticks without an apple, so
+    /// an agent that has learned to circle safely does not run forever.
+    pub max_idle: Option<u64>,
+    /// Built-in bots sharing the board with the agent, which is always
+    /// snake 0.
+    pub opponents: Vec<AiKind>,
+}
+
+impl EnvConfig {
+    /// The agent alone on `level`, seeing the board as a grid, with an
+    /// idle limit of one tick per cell.
+    pub fn new(level: Level) -> EnvConfig {
+        let cells = (level.bounds.cols * level.bounds.rows) as u64;
+        EnvConfig {
+            level,
+            difficulty: Difficulty::Normal,
+            encoding: Encoding::Grid,
+            rewards: Rewards::default(),
+            max_idle: Some(cells),
+            opponents: Vec::new(),
+        }
+    }
+}
+
+/// What happened in a step besides the reward.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Info {
+    pub score: u32,
+    pub ticks: u64,
+    pub death: Option<Death>,
+    pub outcome: Option<Outcome>,
+    /// The episode was cut off by `max_idle` rather than ending in play.
+    pub truncated: bool,
+}
+
+pub struct Env {
+    config: EnvConfig,
+    game: Game,
+    opponents: Vec<Box<dyn Controller>>,
+    idle: u64,
+    truncated: bool,
+}
+
+impl Env {
+    /// An environment ready to play seed 0; call `reset` to pick another.
+    /// Fails if the agent and its opponents do not all fit on the level.
+    pub fn new(config: EnvConfig) -> Result<Env, String> {
+        let game = Game::with_snakes(
+            &config.level,
+            1 + config.opponents.len(),
+            config.difficulty,
+            0,
+        )?;
+        Ok(Env {
+            opponents: config.opponents.iter().map(|ai| ai.controller()).collect(),
+            config,
+            game,
+            idle: 0,
+            truncated: false,
+        })
+    }
+    /// Starts a new episode. The same seed and the same moves always play
+    /// out the same way.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        let config = &self.config;
+        self.game = Game::with_snakes(
+            &config.level,
+            1 + config.opponents.len(),
+            config.difficulty,
+            seed,
+        )
+        .expect("the snakes fit when the environment was made");
+        self.opponents = config.opponents.iter().map(|ai| ai.controller()).collect();
+        self.idle = 0;
+        self.truncated = false;
+        self.observe()
+    }
+    /// Plays one tick with the agent making `action`. Once the episode is
+    /// over, further steps change nothing and earn nothing.
+    pub fn step(&mut self, action: Move) -> (Observation, f32, bool, Info) {
+        if self.is_done() {
+            return (self.observe(), 0.0, true, self.info());
+        }
+        let rewards = self.config.rewards;
+        let me = &self.game.players[0];
+        let dir = action.direction(me.snake.curr_dir);
+        let before = manhattan(&self.game.bounds, me.snake.head(), self.game.apple.pos);
+        let score = me.score.val;
+
+        let mut inputs = vec![Some(dir)];
+        for (i, bot) in self.opponents.iter_mut().enumerate() {
+            let snake = i + 1;
+            inputs.push(if self.game.players[snake].alive {
+                bot.next_move(&Snapshot::new(&self.game, snake))
+            } else {
+                None
+            });
+        }
+        // Where the apple was matters for shaping, since eating moves it.
+        let apple = self.game.apple.pos;
+        self.game.step(&inputs);
+
+        let me = &self.game.players[0];
+        let mut reward = 0.0;
+        if me.alive {
+            reward += rewards.step;
+            let after = manhattan(&self.game.bounds, me.snake.head(), apple);
+            reward += rewards.approach * (before - after) as f32;
+        } else {
+            reward += rewards.death;
+        }
+        if me.score.val > score {
+            reward += rewards.apple;
+            self.idle = 0;
+        } else {
+            self.idle += 1;
+        }
+        match self.game.outcome() {
+            Some(Outcome::BoardFull) | Some(Outcome::GoalReached) | Some(Outcome::Winner(0)) => {
+                reward += rewards.win
+            }
+            _ => {}
+        }
+        if !self.is_done() && self.config.max_idle.is_some_and(|max| self.idle >= max) {
+            self.truncated = true;
+        }
+        (self.observe(), reward, self.is_done(), self.info())
+    }
+    pub fn is_done(&self) -> bool {
+        self.truncated || self.game.is_over() || !self.game.players[0].alive
+    }
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+    pub fn info(&self) -> Info {
+        let me = &self.game.players[0];
+        Info {
+            score: me.score.val,
+            ticks: self.game.ticks(),
+            death: me.death,
+            outcome: self.game.outcome(),
+            truncated: self.truncated,
+        }
+    }
+    /// The current state in the configured encoding.
+    pub fn observe(&self) -> Observation {
+        match self.config.encoding {
+            Encoding::Grid => self.grid(),
+            Encoding::Rays => self.rays(),
+            Encoding::Apple => self.apple_vector(),
+        }
+    }
+
+    fn grid(&self) -> Observation {
+        let bounds = self.game.bounds;
+        let (cols, rows) = (bounds.cols as usize, bounds.rows as usize);
+        let mut data = vec![0.0; GRID_CHANNELS * rows * cols];
+        let mut set = |channel: usize, pos: GridPos| {
+            if bounds.contains(pos) {
+                data[(channel * rows + pos.row as usize) * cols + pos.col as usize] = 1.0;
+            }
+        };
+        for &wall in &self.game.walls {
+            set(0, wall);
+        }
+        for (i, p) in self.game.players.iter().enumerate() {
+            if !p.alive {
+                continue;
+            }
+            for &cell in &p.snake.body {
+                set(if i == 0 { 1 } else { 3 }, cell);
+            }
+            if i == 0 {
+                set(2, p.snake.head());
+            }
+        }
+        set(4, self.game.apple.pos);
+        Observation {
+            data,
+            shape: vec![GRID_CHANNELS, rows, cols],
+        }
+    }
+
+    fn rays(&self) -> Observation {
+        let bounds = self.game.bounds;
+        let head = self.game.players[0].snake.head();
+        let reach = bounds.cols.max(bounds.rows);
+        let mut data = Vec::with_capacity(RAYS.len() * 3);
+        for &(dc, dr) in &RAYS {
+            let (mut wall, mut body, mut apple) = (0.0, 0.0, 0.0);
+            let mut pos = head;
+            for dist in 1..=reach {
+                pos = diagonal_step(&bounds, pos, dc, dr);
+                let near = 1.0 / dist as f32;
+                if !bounds.contains(pos) || self.game.walls.contains(&pos) {
+                    wall = near;
+                    break;
+                }
+                if body == 0.0 && self.occupied(pos) {
+                    body = near;
+                }
+                if apple == 0.0 && pos == self.game.apple.pos {
+                    apple = near;
+                }
+            }
+            data.extend_from_slice(&[wall, body, apple]);
+        }
+        Observation {
+            data,
+            shape: vec![RAYS.len(), 3],
+        }
+    }
+
+    fn apple_vector(&self) -> Observation {
+        let bounds = self.game.bounds;
+        let snake = &self.game.players[0].snake;
+        let (head, heading) = (snake.head(), snake.curr_dir);
+        let apple = self.game.apple.pos;
+        let dx = signed_delta(head.col, apple.col, bounds.cols, bounds.wrap) as f32;
+        let dy = signed_delta(head.row, apple.row, bounds.rows, bounds.wrap) as f32;
+        let (cols, rows) = (bounds.cols as f32, bounds.rows as f32);
+        // Turn the offset into the snake's frame: ahead, then to its right.
+        let (ahead, right) = match heading {
+            Direction::Up => (-dy / rows, dx / cols),
+            Direction::Down => (dy / rows, -dx / cols),
+            Direction::Left => (-dx / cols, -dy / rows),
+            Direction::Right => (dx / cols, dy / rows),
+        };
+        let mut data = vec![ahead, right];
+        for &action in &Move::ALL {
+            let next = bounds.step(head, action.direction(heading));
+            let blocked =
+                !bounds.contains(next) || self.game.walls.contains(&next) || self.occupied(next);
+            data.push(if blocked { 1.0 } else { 0.0 });
+        }
+        Observation {
+            data,
+            shape: vec![5],
+        }
+    }
+
+    /// Whether any living snake's body covers `pos`.
+    fn occupied(&self, pos: GridPos) -> bool {
+        self.game
+            .players
+            .iter()
+            .any(|p| p.alive && p.snake.body.contains(&pos))
+    }
+}
+
+/// The cell `dc` columns and `dr` rows from `pos`, wrapped onto the board in
+/// wrap mode.
+fn diagonal_step(bounds: &Bounds, pos: GridPos, dc: i32, dr: i32) -> GridPos {
+    let next = GridPos::new(pos.col + dc, pos.row + dr);
+    if bounds.wrap {
+        GridPos::new(
+            next.col.rem_euclid(bounds.cols),
+            next.row.rem_euclid(bounds.rows),
+        )
+    } else {
+        next
+    }
+}
+
+/// `to - from`, or the shorter way round a wrapping board.
+fn signed_delta(from: i32, to: i32, size: i32, wrap: bool) -> i32 {
+    let delta = to - from;
+    if wrap && delta.abs() * 2 > size {
+        delta - size * delta.signum()
+    } else {
+        delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::Survivor;
+
+    /// A 6x4 board with the snake at (1,1) heading right, a wall above it
+    /// and the first apple three cells ahead.
+    fn known(encoding: Encoding) -> Env {
+        let level = Level::parse("---\n.#....\n.S..A.\n......\n......\n").unwrap();
+        let mut config = EnvConfig::new(level);
+        config.encoding = encoding;
+        let mut env = Env::new(config).unwrap();
+        env.reset(0);
+        env
+    }
+
+    fn close(a: &[f32], b: &[f32]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-6)
+    }
+
+    #[test]
+    fn reset_replays_the_same_episode() {
+        let level = Level::empty(Bounds::new(12, 10, false));
+        let mut config = EnvConfig::new(level);
+        config.opponents = vec![AiKind::Survivor];
+        config.encoding = Encoding::Rays;
+        let mut env = Env::new(config).unwrap();
+        let episode = |env: &mut Env| {
+            let start = env.reset(9);
+            let mut steps = Vec::new();
+            for _ in 0..200 {
+                // Let the survival bot pick the agent's moves.
+                let heading = env.game().players[0].snake.curr_dir;
+                let dir = Survivor.next_move(&Snapshot::new(env.game(), 0));
+                let action = Move::ALL
+                    .iter()
+                    .copied()
+                    .find(|m| Some(m.direction(heading)) == dir)
+                    .unwrap_or(Move::Straight);
+                let step = env.step(action);
+                let done = step.2;
+                steps.push(step);
+                if done {
+                    break;
+                }
+            }
+            (start, steps)
+        };
+        let first = episode(&mut env);
+        assert!(first.1.len() > 100);
+        assert_eq!(episode(&mut env), first);
+    }
+
+    #[test]
+    fn grid_marks_each_feature_on_its_own_plane() {
+        let obs = known(Encoding::Grid).observe();
+        assert_eq!(obs.shape, vec![GRID_CHANNELS, 4, 6]);
+        assert_eq!(obs.data.len(), GRID_CHANNELS * 4 * 6);
+        let at = |channel: usize, col: usize, row: usize| obs.data[(channel * 4 + row) * 6 + col];
+        assert_eq!(at(0, 1, 0), 1.0);
+        assert_eq!((at(1, 1, 1), at(1, 0, 1)), (1.0, 1.0));
+        assert_eq!((at(2, 1, 1), at(2, 0, 1)), (1.0, 0.0));
+        assert_eq!(at(4, 4, 1), 1.0);
+        assert_eq!(obs.data.iter().sum::<f32>(), 5.0);
+    }
+
+    #[test]
+    fn rays_see_walls_bodies_and_the_apple() {
+        let obs = known(Encoding::Rays).observe();
+        assert_eq!(obs.shape, vec![8, 3]);
+        assert_eq!(obs.data.len(), 24);
+        // North runs straight into the wall, east meets the apple three
+        // cells out and the edge five out, west the tail and then the edge.
+        assert!(close(&obs.data[0..3], &[1.0, 0.0, 0.0]));
+        assert!(close(&obs.data[6..9], &[0.2, 0.0, 1.0 / 3.0]));
+        assert!(close(&obs.data[18..21], &[0.5, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn apple_vector_is_in_the_snakes_frame() {
+        let obs = known(Encoding::Apple).observe();
+        assert_eq!(obs.shape, vec![5]);
+        // The apple is half the board ahead; turning left hits the wall.
+        assert!(close(&obs.data, &[0.5, 0.0, 0.0, 1.0, 0.0]));
+    }
+
+    #[test]
+    fn rewards_add_up_for_each_event() {
+        let mut env = known(Encoding::Apple);
+        env.config.rewards = Rewards {
+            apple: 1.0,
+            death: -1.0,
+            win: 1.0,
+            step: -0.01,
+            approach: 0.1,
+        };
+        let (_, reward, done, _) = env.step(Move::Straight);
+        assert!((reward - 0.09).abs() < 1e-6 && !done);
+        env.step(Move::Straight);
+        let (_, reward, _, info) = env.step(Move::Straight);
+        assert!((reward - 1.09).abs() < 1e-6);
+        assert_eq!(info.score, 1);
+        env.step(Move::Straight);
+        let (_, reward, done, info) = env.step(Move::Straight);
+        assert_eq!((reward, done), (-1.0, true));
+        assert_eq!(info.death, Some(Death::Edge));
+        let (_, reward, done, _) = env.step(Move::Left);
+        assert_eq!((reward, done), (0.0, true));
+    }
+
+    #[test]
+    fn idling_too_long_truncates_the_episode() {
+        let mut rows = vec!["..........".to_string(); 10];
+        rows[1] = ".S........".to_string();
+        rows[8] = "........A.".to_string();
+        let level = Level::parse(&format!("---\n{}\n", rows.join("\n"))).unwrap();
+        assert_eq!(EnvConfig::new(level.clone()).max_idle, Some(100));
+
+        let mut config = EnvConfig::new(level);
+        config.max_idle = Some(3);
+        let mut env = Env::new(config.clone()).unwrap();
+        env.reset(0);
+        // Turning left every tick circles a 2x2 square away from the apple.
+        for _ in 0..2 {
+            assert!(!env.step(Move::Left).2);
+        }
+        let (_, _, done, info) = env.step(Move::Left);
+        assert!(done && info.truncated);
+        assert_eq!((info.death, info.outcome), (None, None));
+
+        config.max_idle = None;
+        let mut env = Env::new(config).unwrap();
+        env.reset(0);
+        for _ in 0..50 {
+            assert!(!env.step(Move::Left).2);
+        }
+        assert!(!env.info().truncated);
+    }
+}
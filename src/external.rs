@@ -0,0 +1,438 @@
+//! Bots that run as separate programs, talking to the game over their
+//! standard input and output.
+//!
+//! Every message is one line of JSON. At the start of each game the bot is
+//! sent
+//!
+//! ```text
+//! {"type":"start","you":0,"cols":20,"rows":15,"wrap":false,
+//!  "walls":[[0,0],...],"snakes":2,"time_limit_ms":100}
+//! ```
+//!
+//! and then on every tick
+//!
+//! ```text
+//! {"type":"tick","tick":0,"you":0,"apple":[7,3],
+//!  "snakes":[{"alive":true,"score":0,"heading":"right",
+//!             "body":[[5,7],[4,7]]},...]}
+//! ```
+//!
+//! where `you` is the index of the bot's own snake, cells are
+//! `[column, row]` from the top-left corner and bodies run from head to
+//! tail. The bot answers each tick with one line, `{"move":"up"}` (or
+//! `down`, `left`, `right`), or `{"move":null}` to carry straight on. Only
+//! the first line after each tick counts; anything more is thrown away
+//! before the next tick is sent. A bot that exits, sends anything else or
+//! takes longer than the time limit to answer is disqualified, and its
+//! snake is taken off the board.
+
+use crate::ai::AiKind;
+use crate::controller::{Controller, Snapshot};
+use crate::game::{Direction, GridPos};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a bot gets to answer its very first tick, which also covers
+/// starting up its interpreter or runtime.
+const STARTUP_GRACE: Duration = Duration::from_secs(2);
+
+/// A bot to play: one of the built-ins, or a program given as
+/// `cmd:<command line>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BotSpec {
+    Builtin(AiKind),
+    External(String),
+}
+
+impl FromStr for BotSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BotSpec, String> {
+        match s.strip_prefix("cmd:") {
+            Some(command) if !command.trim().is_empty() => {
+                Ok(BotSpec::External(command.trim().to_string()))
+            }
+            Some(_) => Err("expected a command after `cmd:`".to_string()),
+            None => s.parse().map(BotSpec::Builtin).map_err(|_| {
+                format!(
+                    "expected greedy, bfs, survival, hamilton or cmd:<command>, found {:?}",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+impl fmt::Display for BotSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotSpec::Builtin(kind) => kind.fmt(f),
+            BotSpec::External(command) => write!(f, "cmd:{}", command),
+        }
+    }
+}
+
+impl BotSpec {
+    /// Starts the bot. External bots are given `time_limit` to answer each
+    /// tick.
+    pub fn controller(&self, time_limit: Duration) -> io::Result<Box<dyn Controller>> {
+        match self {
+            BotSpec::Builtin(kind) => Ok(kind.controller()),
+            BotSpec::External(command) => Ok(Box::new(ExternalBot::spawn(command, time_limit)?)),
+        }
+    }
+}
+
+/// A bot running as a child process. See the module documentation for
+/// what it is sent and has to answer.
+pub struct ExternalBot {
+    child: Child,
+    /// Messages for the bot, written on a separate thread so a bot that
+    /// stops reading cannot block the game once the pipe fills up.
+    messages: Sender<String>,
+    /// One `()` for each message the writer thread has finished writing.
+    written: Receiver<()>,
+    /// Lines the bot has written, read on a separate thread so a bot that
+    /// never answers cannot block the game.
+    lines: Receiver<String>,
+    time_limit: Duration,
+    /// The tick last sent, to tell when a new game has started.
+    last_tick: Option<u64>,
+    answered: bool,
+    failure: Option<String>,
+}
+
+impl ExternalBot {
+    /// Runs `command`, split on whitespace into the program and its
+    /// arguments. The bot's standard error goes to ours.
+    pub fn spawn(command: &str, time_limit: Duration) -> io::Result<ExternalBot> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty bot command"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", program, e)))?;
+        let mut stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (messages, outbox) = mpsc::channel::<String>();
+        let (done, written) = mpsc::channel();
+        thread::spawn(move || {
+            for message in outbox {
+                let result = writeln!(stdin, "{}", message).and_then(|()| stdin.flush());
+                if result.is_err() || done.send(()).is_err() {
+                    break;
+                }
+            }
+        });
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(ExternalBot {
+            child,
+            messages,
+            written,
+            lines,
+            time_limit,
+            last_tick: None,
+            answered: false,
+            failure: None,
+        })
+    }
+    /// Sends `message`, returning false if the bot has not read it by
+    /// `deadline` or can no longer be written to.
+    fn send(&self, message: &Value, deadline: Instant) -> bool {
+        if self.messages.send(message.to_string()).is_err() {
+            return false;
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        self.written.recv_timeout(left).is_ok()
+    }
+    /// Gives up on the bot for good.
+    fn fail(&mut self, reason: String) -> Option<Direction> {
+        self.failure = Some(reason);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        None
+    }
+}
+
+impl Drop for ExternalBot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn cell(pos: GridPos) -> Value {
+    json!([pos.col, pos.row])
+}
+
+fn start_message(snapshot: &Snapshot, time_limit: Duration) -> Value {
+    json!({
+        "type": "start",
+        "you": snapshot.me,
+        "cols": snapshot.bounds.cols,
+        "rows": snapshot.bounds.rows,
+        "wrap": snapshot.bounds.wrap,
+        "walls": snapshot.walls.iter().map(|&w| cell(w)).collect::<Vec<_>>(),
+        "snakes": snapshot.players.len(),
+        "time_limit_ms": time_limit.as_millis() as u64,
+    })
+}
+
+fn tick_message(snapshot: &Snapshot) -> Value {
+    let snakes: Vec<Value> = snapshot
+        .players
+        .iter()
+        .map(|p| {
+            json!({
+                "alive": p.alive,
+                "score": p.score.val,
+                "heading": p.snake.curr_dir.to_string(),
+                "body": p.snake.body.iter().map(|&c| cell(c)).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    json!({
+        "type": "tick",
+        "tick": snapshot.tick,
+        "you": snapshot.me,
+        "apple": cell(snapshot.apple),
+        "snakes": snakes,
+    })
+}
+
+/// The move in a reply line, or `Err` if the line is not a valid reply.
+fn parse_reply(line: &str) -> Result<Option<Direction>, ()> {
+    let reply: Value = serde_json::from_str(line).map_err(|_| ())?;
+    match reply.get("move") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(dir)) => dir.parse().map(Some).map_err(|_| ()),
+        Some(_) => Err(()),
+    }
+}
+
+impl Controller for ExternalBot {
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction> {
+        if self.failure.is_some() {
+            return None;
+        }
+        let limit = if self.answered {
+            self.time_limit
+        } else {
+            self.time_limit.max(STARTUP_GRACE)
+        };
+        // Writing the tick and reading the answer share the time limit.
+        let deadline = Instant::now() + limit;
+        let new_game = self.last_tick.is_none_or(|last| snapshot.tick <= last);
+        self.last_tick = Some(snapshot.tick);
+        // Lines left over from earlier ticks would otherwise be read as the
+        // answer to this one.
+        while self.lines.try_recv().is_ok() {}
+        let sent = (!new_game || self.send(&start_message(snapshot, self.time_limit), deadline))
+            && self.send(&tick_message(snapshot), deadline);
+        if !sent {
+            return match self.child.try_wait() {
+                Ok(Some(_)) => self.fail("exited".to_string()),
+                _ => self.fail("stopped reading its input".to_string()),
+            };
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(left) {
+            Ok(line) => {
+                self.answered = true;
+                match parse_reply(&line) {
+                    Ok(dir) => dir,
+                    Err(()) => self.fail(format!("sent an invalid reply {:?}", line)),
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                self.fail(format!("took longer than {} ms to move", limit.as_millis()))
+            }
+            Err(RecvTimeoutError::Disconnected) => self.fail("exited".to_string()),
+        }
+    }
+    fn failure(&self) -> Option<&str> {
+        self.failure.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::game::{Bounds, Game};
+    use crate::level::Level;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const TIME_LIMIT: Duration = Duration::from_millis(200);
+
+    /// A script file, removed again when dropped.
+    struct Script(PathBuf);
+
+    impl Drop for Script {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    /// Starts a bot running the shell script `text`, written to a file of
+    /// its own that lasts as long as the returned `Script`.
+    fn bot(name: &str, text: &str) -> (Script, ExternalBot) {
+        let path = env::temp_dir().join(format!("snake-bot-{}-{}.sh", std::process::id(), name));
+        fs::write(&path, text).unwrap();
+        let bot = ExternalBot::spawn(&format!("sh {}", path.display()), TIME_LIMIT).unwrap();
+        (Script(path), bot)
+    }
+
+    fn game() -> Game {
+        let level = Level::empty(Bounds::new(10, 8, false));
+        Game::new(&level, Difficulty::Normal, 0)
+    }
+
+    /// Plays `ticks` ticks of a fresh game with `bot`, returning its moves.
+    fn moves(bot: &mut ExternalBot, ticks: usize) -> Vec<Option<Direction>> {
+        let mut game = game();
+        let mut moves = Vec::new();
+        for _ in 0..ticks {
+            let dir = bot.next_move(&Snapshot::new(&game, 0));
+            moves.push(dir);
+            game.step(&[None]);
+            // Gives a bot that says too much time to say it.
+            thread::sleep(Duration::from_millis(20));
+        }
+        moves
+    }
+
+    #[test]
+    fn replies_parse_to_moves() {
+        assert_eq!(parse_reply(r#"{"move":"up"}"#), Ok(Some(Direction::Up)));
+        assert_eq!(
+            parse_reply(r#" {"move": "left", "note": 1} "#),
+            Ok(Some(Direction::Left))
+        );
+        assert_eq!(parse_reply(r#"{"move":null}"#), Ok(None));
+        assert_eq!(parse_reply("{}"), Ok(None));
+        assert!(parse_reply(r#"{"move":"sideways"}"#).is_err());
+        assert!(parse_reply(r#"{"move":2}"#).is_err());
+        assert!(parse_reply("up").is_err());
+        assert!(parse_reply("").is_err());
+    }
+
+    #[test]
+    fn messages_describe_the_board() {
+        let game = game();
+        let snapshot = Snapshot::new(&game, 0);
+        let start = start_message(&snapshot, TIME_LIMIT);
+        assert_eq!(start["type"], "start");
+        assert_eq!(
+            (start["cols"].as_i64(), start["rows"].as_i64()),
+            (Some(10), Some(8))
+        );
+        assert_eq!(start["wrap"], false);
+        assert_eq!(start["walls"], json!([]));
+        assert_eq!(
+            (start["you"].as_u64(), start["snakes"].as_u64()),
+            (Some(0), Some(1))
+        );
+        assert_eq!(start["time_limit_ms"], 200);
+
+        let tick = tick_message(&snapshot);
+        assert_eq!(tick["type"], "tick");
+        assert_eq!(tick["tick"], 0);
+        assert_eq!(tick["apple"], cell(game.apple.pos));
+        let snake = &tick["snakes"][0];
+        assert_eq!(snake["alive"], true);
+        assert_eq!(snake["heading"], "right");
+        assert_eq!(snake["body"], json!([[5, 4], [4, 4]]));
+        assert_eq!(
+            serde_json::from_str::<Value>(&tick.to_string()).unwrap(),
+            tick
+        );
+    }
+
+    #[test]
+    fn answers_are_read_each_tick() {
+        let (_script, mut bot) = bot(
+            "echo",
+            r#"while read line; do
+                 case "$line" in *'"tick"'*) echo '{"move":"down"}' ;; esac
+               done"#,
+        );
+        assert_eq!(moves(&mut bot, 3), vec![Some(Direction::Down); 3]);
+        assert_eq!(bot.failure(), None);
+    }
+
+    #[test]
+    fn extra_lines_do_not_answer_later_ticks() {
+        let (_script, mut bot) = bot(
+            "chatty",
+            r#"while read line; do
+                 case "$line" in *'"tick"'*)
+                   echo '{"move":"up"}'
+                   echo '{"move":"left"}'
+                   echo 'and some noise' ;;
+                 esac
+               done"#,
+        );
+        assert_eq!(moves(&mut bot, 4), vec![Some(Direction::Up); 4]);
+        assert_eq!(bot.failure(), None);
+    }
+
+    #[test]
+    fn a_slow_bot_is_disqualified() {
+        let (_script, mut bot) = bot(
+            "slow",
+            r#"read start; read tick; echo '{"move":null}'; sleep 5"#,
+        );
+        assert_eq!(moves(&mut bot, 3), vec![None; 3]);
+        let failure = bot.failure().unwrap();
+        assert!(
+            failure.starts_with("took longer than 200 ms"),
+            "{}",
+            failure
+        );
+    }
+
+    #[test]
+    fn a_bot_that_exits_is_disqualified() {
+        let (_script, mut bot) = bot("quitter", r#"read start; read tick; echo '{"move":null}'"#);
+        moves(&mut bot, 3);
+        assert_eq!(bot.failure(), Some("exited"));
+    }
+
+    #[test]
+    fn a_bad_reply_is_disqualified() {
+        let (_script, mut bot) = bot("rude", "read start; read tick; echo go away; sleep 5");
+        assert_eq!(moves(&mut bot, 2), vec![None; 2]);
+        let failure = bot.failure().unwrap();
+        assert!(failure.starts_with("sent an invalid reply"), "{}", failure);
+    }
+
+    #[test]
+    fn bot_specs_parse() {
+        assert_eq!("bfs".parse(), Ok(BotSpec::Builtin(AiKind::Pathfinder)));
+        let spec: BotSpec = "cmd: python3 bot.py ".parse().unwrap();
+        assert_eq!(spec, BotSpec::External("python3 bot.py".to_string()));
+        assert_eq!(spec.to_string(), "cmd:python3 bot.py");
+        assert!("cmd:".parse::<BotSpec>().is_err());
+        assert!("clever".parse::<BotSpec>().is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use crate::game::GridPos;
+use rand::Rng;
+
+/// The set of board cells not covered by a snake.
+///
+/// Free cells are kept densely packed in a vector with a reverse index, so
+/// occupying, releasing and picking a uniformly random free cell are all
+/// O(1) no matter how full the board is.
+pub struct FreeCells {
+    cols: i32,
+    rows: i32,
+    cells: Vec<GridPos>,
+    index: Vec<Option<usize>>,
+}
+
+impl FreeCells {
+    pub fn new(cols: i32, rows: i32) -> FreeCells {
+        let mut cells = Vec::with_capacity((cols * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                cells.push(GridPos::new(col, row));
+            }
+        }
+        let index = (0..cells.len()).map(Some).collect();
+        FreeCells {
+            cols,
+            rows,
+            cells,
+            index,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+    pub fn contains(&self, pos: GridPos) -> bool {
+        self.slot(pos)
+            .is_some_and(|slot| self.index[slot].is_some())
+    }
+    /// Marks `pos` as covered. Positions off the board are ignored.
+    pub fn occupy(&mut self, pos: GridPos) {
+        let slot = match self.slot(pos) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if let Some(i) = self.index[slot].take() {
+            self.cells.swap_remove(i);
+            if let Some(&moved) = self.cells.get(i) {
+                let moved_slot = self.slot(moved).unwrap();
+                self.index[moved_slot] = Some(i);
+            }
+        }
+    }
+    /// Marks `pos` as free again. Positions off the board are ignored.
+    pub fn release(&mut self, pos: GridPos) {
+        let slot = match self.slot(pos) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if self.index[slot].is_none() {
+            self.index[slot] = Some(self.cells.len());
+            self.cells.push(pos);
+        }
+    }
+    /// A uniformly chosen free cell, or `None` when the board is full.
+    pub fn random<R: Rng>(&self, rng: &mut R) -> Option<GridPos> {
+        if self.cells.is_empty() {
+            None
+        } else {
+            Some(self.cells[rng.gen_range(0, self.cells.len())])
+        }
+    }
+    fn slot(&self, pos: GridPos) -> Option<usize> {
+        if pos.col >= 0 && pos.col < self.cols && pos.row >= 0 && pos.row < self.rows {
+            Some((pos.row * self.cols + pos.col) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game;
+
+    #[test]
+    fn occupy_and_release_track_free_cells() {
+        let mut free = FreeCells::new(3, 2);
+        assert_eq!(free.len(), 6);
+        free.occupy(GridPos::new(1, 1));
+        free.occupy(GridPos::new(1, 1));
+        assert_eq!(free.len(), 5);
+        assert!(!free.contains(GridPos::new(1, 1)));
+        assert!(free.contains(GridPos::new(2, 1)));
+        free.release(GridPos::new(1, 1));
+        free.release(GridPos::new(1, 1));
+        assert_eq!(free.len(), 6);
+        assert!(free.contains(GridPos::new(1, 1)));
+    }
+
+    #[test]
+    fn cells_off_the_board_are_ignored() {
+        let mut free = FreeCells::new(3, 2);
+        free.occupy(GridPos::new(-1, 0));
+        free.occupy(GridPos::new(3, 0));
+        free.release(GridPos::new(0, 2));
+        assert_eq!(free.len(), 6);
+        assert!(!free.contains(GridPos::new(0, -1)));
+    }
+
+    #[test]
+    fn random_picks_only_free_cells() {
+        let mut free = FreeCells::new(3, 2);
+        for col in 0..3 {
+            free.occupy(GridPos::new(col, 0));
+        }
+        free.occupy(GridPos::new(0, 1));
+        let mut rng = game::rng_from_seed(7);
+        for _ in 0..50 {
+            let pos = free.random(&mut rng).unwrap();
+            assert!(pos == GridPos::new(1, 1) || pos == GridPos::new(2, 1));
+        }
+        free.occupy(GridPos::new(1, 1));
+        free.occupy(GridPos::new(2, 1));
+        assert!(free.is_empty());
+        assert_eq!(free.random(&mut rng), None);
+    }
+}
@@ -0,0 +1,735 @@
+use crate::difficulty::Difficulty;
+use crate::free_cells::FreeCells;
+use crate::level::{Goal, Level};
+use rand::prng::XorShiftRng;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Not;
+use std::str::FromStr;
+
+/// A cell on the board, counted from the top-left corner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GridPos {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl GridPos {
+    pub fn new(col: i32, row: i32) -> GridPos {
+        GridPos { col, row }
+    }
+    pub fn next_to(self, dir: Direction) -> GridPos {
+        match dir {
+            Direction::Up => GridPos::new(self.col, self.row - 1),
+            Direction::Down => GridPos::new(self.col, self.row + 1),
+            Direction::Left => GridPos::new(self.col - 1, self.row),
+            Direction::Right => GridPos::new(self.col + 1, self.row),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Apple {
+    pub pos: GridPos,
+}
+
+pub struct Snake {
+    pub body: VecDeque<GridPos>,
+    pub curr_dir: Direction,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(format!("expected up, down, left or right, found {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        })
+    }
+}
+
+impl Not for Direction {
+    type Output = Direction;
+
+    fn not(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
+impl Direction {
+    /// The direction a quarter turn anticlockwise, as seen from above.
+    pub fn turn_left(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+    /// The direction a quarter turn clockwise.
+    pub fn turn_right(self) -> Direction {
+        !self.turn_left()
+    }
+}
+
+impl Snake {
+    fn new(head: GridPos, dir: Direction, bounds: &Bounds) -> Snake {
+        let body = vec![head, bounds.step(head, !dir)].into_iter().collect();
+        Snake {
+            body,
+            curr_dir: dir,
+        }
+    }
+    fn shorten_tail(&mut self) -> GridPos {
+        self.body.pop_back().unwrap()
+    }
+    fn advance(&mut self, bounds: &Bounds) {
+        let new_head = bounds.step(self.head(), self.curr_dir);
+        self.body.push_front(new_head);
+    }
+    pub fn head(&self) -> GridPos {
+        *self.body.front().unwrap()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Bounds {
+    pub cols: i32,
+    pub rows: i32,
+    /// Leaving one edge re-enters from the opposite one instead of dying.
+    pub wrap: bool,
+}
+
+impl Bounds {
+    pub fn new(cols: i32, rows: i32, wrap: bool) -> Bounds {
+        Bounds { cols, rows, wrap }
+    }
+    /// The cell one move from `pos` in `dir`, wrapped onto the board in wrap
+    /// mode.
+    pub fn step(&self, pos: GridPos, dir: Direction) -> GridPos {
+        let next = pos.next_to(dir);
+        if self.wrap {
+            GridPos::new(
+                next.col.rem_euclid(self.cols),
+                next.row.rem_euclid(self.rows),
+            )
+        } else {
+            next
+        }
+    }
+    pub fn middle(&self) -> GridPos {
+        GridPos::new(self.cols / 2, self.rows / 2)
+    }
+    pub fn contains(&self, pos: GridPos) -> bool {
+        pos.col >= 0 && pos.col < self.cols && pos.row >= 0 && pos.row < self.rows
+    }
+}
+
+#[derive(Default)]
+pub struct Score {
+    pub val: u32,
+}
+
+impl Score {
+    fn increment(&mut self) {
+        self.val += 1;
+    }
+}
+
+/// Something that happened during a single `Game::step`, for the frontend to
+/// render or play a sound for. Snakes are identified by their index in
+/// `Game::players`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Moved,
+    AteApple(usize),
+    Died(usize),
+    /// The game ended with a winner: the level's goal was met, the board
+    /// filled up, or only one snake is left.
+    Won,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The only snake died.
+    Died,
+    BoardFull,
+    GoalReached,
+    /// Every other snake is dead, or this one met the goal first.
+    Winner(usize),
+    /// The last snakes died in the same tick, or met the goal together.
+    Draw,
+}
+
+/// What a snake ran into.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Death {
+    /// Left the board, which only happens without wrapping.
+    Edge,
+    Wall,
+    /// Its own body, tail included.
+    Itself,
+    /// Another snake's body.
+    Snake,
+    /// Another snake's head, moving into the same cell.
+    HeadOn,
+    /// Taken off the board because whatever steered it failed.
+    Disqualified,
+}
+
+impl fmt::Display for Death {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Death::Edge => "edge",
+            Death::Wall => "wall",
+            Death::Itself => "own body",
+            Death::Snake => "other snake",
+            Death::HeadOn => "head-on",
+            Death::Disqualified => "disqualified",
+        })
+    }
+}
+
+/// One snake on the board and how it is doing.
+pub struct Player {
+    pub snake: Snake,
+    pub score: Score,
+    pub alive: bool,
+    /// Why the snake died, once it has.
+    pub death: Option<Death>,
+}
+
+/// The whole simulation: the snakes, one apple, the board, its walls and
+/// the scores.
+///
+/// Nothing in here keeps time; the caller decides when a tick happens
+/// (`tick_interval` says how long one should last) and calls `step` once per
+/// tick. All randomness comes from an RNG seeded
+/// with `seed`, so the same seed and the same inputs replay the same game.
+pub struct Game {
+    pub players: Vec<Player>,
+    pub apple: Apple,
+    pub bounds: Bounds,
+    pub walls: Vec<GridPos>,
+    free: FreeCells,
+    fixed_apples: VecDeque<GridPos>,
+    goal: Option<Goal>,
+    difficulty: Difficulty,
+    start_speed: u64,
+    ticks: u64,
+    elapsed_ms: u64,
+    seed: u64,
+    rng: XorShiftRng,
+    outcome: Option<Outcome>,
+}
+
+/// One step of SplitMix64, which spreads nearby seeds far apart.
+fn split_mix(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+pub(crate) fn rng_from_seed(seed: u64) -> XorShiftRng {
+    // XorShift's first draws follow its seed bytes closely, so seeds one
+    // apart would start out as near enough the same game without mixing.
+    let mut state = seed;
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&split_mix(&mut state).to_le_bytes());
+    bytes[8..].copy_from_slice(&split_mix(&mut state).to_le_bytes());
+    XorShiftRng::from_seed(bytes)
+}
+
+/// The level's next fixed apple cell that is still free, or else a random
+/// free cell.
+fn place_apple(
+    free: &FreeCells,
+    fixed: &mut VecDeque<GridPos>,
+    rng: &mut XorShiftRng,
+) -> Option<GridPos> {
+    while let Some(pos) = fixed.pop_front() {
+        if free.contains(pos) {
+            return Some(pos);
+        }
+    }
+    free.random(rng)
+}
+
+/// Cells a new snake must keep clear of the other snakes' bodies, so no
+/// two snakes start already touching.
+const START_GAP: i32 = 4;
+
+/// Where snake number `player` starts. The first snake uses the level's
+/// start; the others start mirrored through the middle of the board, facing
+/// the other way, or failing that on the nearest cell to there with room
+/// to move. Cramped boards settle for any two free cells, and `None` means
+/// there are none left.
+fn start_for(
+    level: &Level,
+    player: usize,
+    free: &FreeCells,
+    taken: &[GridPos],
+) -> Option<(GridPos, Direction)> {
+    if player == 0 {
+        return Some((level.start, level.start_dir));
+    }
+    let bounds = &level.bounds;
+    let fits = |pos: GridPos, dir: Direction, roomy: bool| {
+        let tail = bounds.step(pos, !dir);
+        let ahead = bounds.step(pos, dir);
+        let clear = |cell: GridPos| bounds.contains(cell) && free.contains(cell);
+        if !clear(pos) || !clear(tail) {
+            return false;
+        }
+        !roomy
+            || clear(ahead)
+                && clear(bounds.step(ahead, dir))
+                && taken.iter().all(|other| {
+                    (other.col - pos.col).abs().max((other.row - pos.row).abs()) >= START_GAP
+                })
+    };
+    let mirror = GridPos::new(
+        bounds.cols - 1 - level.start.col,
+        bounds.rows - 1 - level.start.row,
+    );
+    let dir = !level.start_dir;
+    let dirs = [dir, !dir, dir.turn_left(), dir.turn_right()];
+    let mut cells: Vec<GridPos> = (0..bounds.rows)
+        .flat_map(|row| (0..bounds.cols).map(move |col| GridPos::new(col, row)))
+        .collect();
+    cells.sort_by_key(|pos| (pos.col - mirror.col).abs() + (pos.row - mirror.row).abs());
+    for &roomy in &[true, false] {
+        for &pos in &cells {
+            if let Some(&dir) = dirs.iter().find(|&&dir| fits(pos, dir, roomy)) {
+                return Some((pos, dir));
+            }
+        }
+    }
+    None
+}
+
+impl Game {
+    /// A single-snake game. Every level `Level::parse` accepts, and every
+    /// built-in layout, has room for it.
+    pub fn new(level: &Level, difficulty: Difficulty, seed: u64) -> Game {
+        Game::with_snakes(level, 1, difficulty, seed).expect("no room for the snake")
+    }
+    /// A game with `snakes` snakes sharing the board and its apples, or an
+    /// error if they do not all fit with a cell left for the apple. Whether
+    /// they fit does not depend on `seed`.
+    pub fn with_snakes(
+        level: &Level,
+        snakes: usize,
+        difficulty: Difficulty,
+        seed: u64,
+    ) -> Result<Game, String> {
+        let mut rng = rng_from_seed(seed);
+        let bounds = level.bounds;
+        // Walls are never released, so they stay out of the free set for
+        // both collisions and apple placement.
+        let mut free = FreeCells::new(bounds.cols, bounds.rows);
+        for &cell in &level.walls {
+            free.occupy(cell);
+        }
+        let mut players = Vec::with_capacity(snakes);
+        for player in 0..snakes {
+            let taken: Vec<GridPos> = players
+                .iter()
+                .flat_map(|p: &Player| p.snake.body.iter().copied())
+                .collect();
+            let (start, dir) = start_for(level, player, &free, &taken)
+                .ok_or_else(|| format!("board too small for {} snakes", snakes))?;
+            let snake = Snake::new(start, dir, &bounds);
+            for &cell in &snake.body {
+                free.occupy(cell);
+            }
+            players.push(Player {
+                snake,
+                score: Score::default(),
+                alive: true,
+                death: None,
+            });
+        }
+        let mut fixed_apples = level.apples.iter().copied().collect();
+        let apple = Apple {
+            pos: place_apple(&free, &mut fixed_apples, &mut rng)
+                .ok_or_else(|| format!("no room for an apple beside {} snakes", snakes))?,
+        };
+        Ok(Game {
+            players,
+            apple,
+            bounds,
+            walls: level.walls.clone(),
+            free,
+            fixed_apples,
+            goal: level.goal,
+            difficulty,
+            start_speed: level.speed.unwrap_or_else(|| difficulty.start()),
+            ticks: 0,
+            elapsed_ms: 0,
+            seed,
+            rng,
+            outcome: None,
+        })
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn is_over(&self) -> bool {
+        self.outcome.is_some()
+    }
+    pub fn outcome(&self) -> Option<Outcome> {
+        self.outcome
+    }
+    /// Ticks played so far.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+    pub fn difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+    /// The highest score of any snake.
+    pub fn best_score(&self) -> u32 {
+        self.players.iter().map(|p| p.score.val).max().unwrap_or(0)
+    }
+    /// Milliseconds the next tick should last. It starts at the level's
+    /// speed, or the difficulty's if the level has none, and shrinks as the
+    /// best score rises.
+    pub fn tick_interval(&self) -> u64 {
+        self.difficulty
+            .interval(self.start_speed, self.best_score())
+    }
+    pub fn goal(&self) -> Option<Goal> {
+        self.goal
+    }
+    /// How far snake `player` is along the goal, as `(done, target)` in
+    /// the goal's own unit. Survival time adds up `tick_interval` over the
+    /// ticks played, so it does not depend on the frame rate.
+    pub fn goal_progress(&self, player: usize) -> Option<(u64, u64)> {
+        let p = &self.players[player];
+        self.goal.map(|goal| match goal {
+            Goal::Apples(n) => (u64::from(p.score.val), u64::from(n)),
+            Goal::Length(n) => (p.snake.body.len() as u64, n as u64),
+            Goal::Survive(secs) => (self.elapsed_ms / 1000, secs),
+        })
+    }
+    fn goal_met(&self, player: usize) -> bool {
+        self.goal_progress(player)
+            .is_some_and(|(done, target)| done >= target)
+    }
+    /// Advances every living snake by one cell, turning first where its
+    /// entry in `inputs` is not a reversal of the current direction.
+    ///
+    /// All snakes move at once. A snake dies if its new head leaves the
+    /// board or lands on a wall or any snake's body as it was before the
+    /// move, including tails about to move on; two heads landing on the same
+    /// cell both die. Dead snakes are then cleared off the board before the
+    /// survivors grow or move their tails.
+    pub fn step(&mut self, inputs: &[Option<Direction>]) -> Vec<Event> {
+        let mut events = Vec::new();
+        if self.is_over() {
+            return events;
+        }
+        self.ticks += 1;
+        self.elapsed_ms += self.tick_interval();
+        let mut heads = Vec::with_capacity(self.players.len());
+        for (i, p) in self.players.iter_mut().enumerate() {
+            if !p.alive {
+                heads.push(None);
+                continue;
+            }
+            if let Some(dir) = inputs.get(i).copied().flatten() {
+                if dir != !p.snake.curr_dir {
+                    p.snake.curr_dir = dir;
+                }
+            }
+            heads.push(Some(self.bounds.step(p.snake.head(), p.snake.curr_dir)));
+        }
+        events.push(Event::Moved);
+
+        let deaths: Vec<Option<Death>> = heads
+            .iter()
+            .enumerate()
+            .map(|(i, head)| head.and_then(|head| self.collision(i, head, &heads)))
+            .collect();
+        for (i, p) in self.players.iter_mut().enumerate() {
+            if let Some(death) = deaths[i] {
+                p.alive = false;
+                p.death = Some(death);
+                for &cell in &p.snake.body {
+                    self.free.release(cell);
+                }
+                events.push(Event::Died(i));
+            }
+        }
+
+        let mut ate = false;
+        for (i, p) in self.players.iter_mut().enumerate() {
+            if !p.alive {
+                continue;
+            }
+            p.snake.advance(&self.bounds);
+            let head = p.snake.head();
+            self.free.occupy(head);
+            if self.apple.pos == head {
+                p.score.increment();
+                events.push(Event::AteApple(i));
+                ate = true;
+            } else {
+                let tail = p.snake.shorten_tail();
+                self.free.release(tail);
+            }
+        }
+        if ate {
+            match place_apple(&self.free, &mut self.fixed_apples, &mut self.rng) {
+                Some(pos) => self.apple.pos = pos,
+                None => self.outcome = Some(Outcome::BoardFull),
+            }
+        }
+        if self.outcome.is_none() {
+            self.outcome = self.decide();
+        }
+        if let Some(outcome) = self.outcome {
+            if outcome != Outcome::Died && outcome != Outcome::Draw {
+                events.push(Event::Won);
+            }
+        }
+        events
+    }
+    /// Takes snake `player` off the board as if it had crashed, for when
+    /// its controller has failed. The game may end because of it.
+    pub fn disqualify(&mut self, player: usize) {
+        let p = &mut self.players[player];
+        if !p.alive || self.outcome.is_some() {
+            return;
+        }
+        p.alive = false;
+        p.death = Some(Death::Disqualified);
+        for &cell in &p.snake.body {
+            self.free.release(cell);
+        }
+        self.outcome = self.decide();
+    }
+    /// What snake `i` runs into by moving its head to `head`, given where
+    /// every living snake's head is going.
+    fn collision(&self, i: usize, head: GridPos, heads: &[Option<GridPos>]) -> Option<Death> {
+        if !self.bounds.contains(head) {
+            Some(Death::Edge)
+        } else if !self.free.contains(head) {
+            Some(if self.players[i].snake.body.contains(&head) {
+                Death::Itself
+            } else if self.walls.contains(&head) {
+                Death::Wall
+            } else {
+                Death::Snake
+            })
+        } else if heads
+            .iter()
+            .enumerate()
+            .any(|(j, other)| j != i && *other == Some(head))
+        {
+            Some(Death::HeadOn)
+        } else {
+            None
+        }
+    }
+    /// How the game ended this tick, if it did.
+    fn decide(&self) -> Option<Outcome> {
+        let alive: Vec<usize> = (0..self.players.len())
+            .filter(|&i| self.players[i].alive)
+            .collect();
+        if self.players.len() == 1 {
+            return if alive.is_empty() {
+                Some(Outcome::Died)
+            } else if self.goal_met(0) {
+                Some(Outcome::GoalReached)
+            } else {
+                None
+            };
+        }
+        match alive.len() {
+            0 => return Some(Outcome::Draw),
+            1 => return Some(Outcome::Winner(alive[0])),
+            _ => {}
+        }
+        let reached: Vec<usize> = alive.into_iter().filter(|&i| self.goal_met(i)).collect();
+        match reached.len() {
+            0 => None,
+            1 => Some(Outcome::Winner(reached[0])),
+            _ => Some(Outcome::Draw),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearby_seeds_place_different_apples() {
+        let level = Level::empty(Bounds::new(80, 60, false));
+        let mut apples: Vec<GridPos> = (0..8)
+            .chain(vec![100, 1000])
+            .map(|seed| Game::new(&level, Difficulty::Normal, seed).apple.pos)
+            .collect();
+        apples.sort_by_key(|pos| (pos.col, pos.row));
+        apples.dedup();
+        assert!(apples.len() >= 9, "apples repeat: {:?}", apples);
+    }
+
+    #[test]
+    fn leaving_the_board_is_an_edge_death() {
+        let level = Level::empty(Bounds::new(6, 4, false));
+        let mut game = Game::new(&level, Difficulty::Normal, 0);
+        game.apple.pos = GridPos::new(0, 0);
+        game.step(&[None]);
+        game.step(&[None]);
+        assert!(game.players[0].alive);
+        let events = game.step(&[None]);
+        assert!(events.contains(&Event::Died(0)));
+        assert_eq!(game.players[0].death, Some(Death::Edge));
+        assert_eq!(game.outcome(), Some(Outcome::Died));
+    }
+
+    #[test]
+    fn eating_grows_the_snake_until_it_bites_itself() {
+        let level = Level::parse("---\n........\n.SAAA...\n........\n........\n").unwrap();
+        let mut game = Game::new(&level, Difficulty::Normal, 0);
+        for _ in 0..3 {
+            let events = game.step(&[None]);
+            assert!(events.contains(&Event::AteApple(0)));
+        }
+        assert_eq!(game.players[0].score.val, 3);
+        assert_eq!(game.players[0].snake.body.len(), 5);
+        // Down, left and up again runs back into the body.
+        game.step(&[Some(Direction::Down)]);
+        game.step(&[Some(Direction::Left)]);
+        assert!(game.players[0].alive);
+        game.step(&[Some(Direction::Up)]);
+        assert_eq!(game.players[0].death, Some(Death::Itself));
+    }
+
+    #[test]
+    fn reversing_is_ignored() {
+        let level = Level::empty(Bounds::new(8, 8, false));
+        let mut game = Game::new(&level, Difficulty::Normal, 0);
+        game.apple.pos = GridPos::new(0, 0);
+        game.step(&[Some(Direction::Left)]);
+        assert!(game.players[0].alive);
+        assert_eq!(game.players[0].snake.curr_dir, Direction::Right);
+    }
+
+    #[test]
+    fn wrapping_survives_the_edge() {
+        let level = Level::empty(Bounds::new(6, 4, true));
+        let mut game = Game::new(&level, Difficulty::Normal, 0);
+        game.apple.pos = GridPos::new(0, 0);
+        for _ in 0..3 {
+            game.step(&[None]);
+        }
+        assert!(game.players[0].alive);
+        assert_eq!(game.players[0].snake.head(), GridPos::new(0, 2));
+    }
+
+    #[test]
+    fn running_into_a_wall_kills() {
+        let level = Level::parse("---\n......\n.S.#..\n......\n......\n").unwrap();
+        let mut game = Game::new(&level, Difficulty::Normal, 0);
+        game.apple.pos = GridPos::new(5, 3);
+        game.step(&[None]);
+        assert!(game.players[0].alive);
+        game.step(&[None]);
+        assert_eq!(game.players[0].death, Some(Death::Wall));
+    }
+
+    /// Moves snake `player` onto `body`, head first, heading `dir`.
+    fn place(game: &mut Game, player: usize, body: &[(i32, i32)], dir: Direction) {
+        for &cell in &game.players[player].snake.body {
+            game.free.release(cell);
+        }
+        let body: VecDeque<GridPos> = body.iter().map(|&(c, r)| GridPos::new(c, r)).collect();
+        for &cell in &body {
+            game.free.occupy(cell);
+        }
+        game.players[player].snake = Snake {
+            body,
+            curr_dir: dir,
+        };
+    }
+
+    /// Two snakes on an open board, with the apple in the far corner.
+    fn two_snakes(cols: i32, rows: i32) -> Game {
+        let level = Level::empty(Bounds::new(cols, rows, false));
+        let mut game = Game::with_snakes(&level, 2, Difficulty::Normal, 0).unwrap();
+        game.apple.pos = GridPos::new(cols - 1, rows - 1);
+        game
+    }
+
+    #[test]
+    fn running_into_another_snake_kills_only_the_runner() {
+        let mut game = two_snakes(10, 6);
+        place(&mut game, 0, &[(3, 2), (2, 2)], Direction::Right);
+        place(&mut game, 1, &[(4, 1), (4, 2), (4, 3)], Direction::Up);
+        game.step(&[None, None]);
+        assert_eq!(game.players[0].death, Some(Death::Snake));
+        assert!(game.players[1].alive);
+        assert_eq!(game.outcome(), Some(Outcome::Winner(1)));
+    }
+
+    #[test]
+    fn heads_meeting_kill_both() {
+        let mut game = two_snakes(10, 6);
+        place(&mut game, 0, &[(3, 2), (2, 2)], Direction::Right);
+        place(&mut game, 1, &[(5, 2), (6, 2)], Direction::Left);
+        game.step(&[None, None]);
+        assert_eq!(game.players[0].death, Some(Death::HeadOn));
+        assert_eq!(game.players[1].death, Some(Death::HeadOn));
+        assert_eq!(game.outcome(), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn snakes_that_do_not_fit_are_an_error() {
+        let level = Level::empty(Bounds::new(4, 4, false));
+        assert!(Game::with_snakes(&level, 2, Difficulty::Normal, 0).is_ok());
+        assert!(Game::with_snakes(&level, 8, Difficulty::Normal, 0).is_err());
+    }
+
+    #[test]
+    fn disqualifying_one_of_two_leaves_a_winner() {
+        let mut game = two_snakes(10, 6);
+        game.disqualify(1);
+        assert_eq!(game.players[1].death, Some(Death::Disqualified));
+        assert_eq!(game.outcome(), Some(Outcome::Winner(0)));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::difficulty::Difficulty;
+use std::fmt::Write;
+
+/// How many runs the table keeps for each board.
+pub const MAX_ENTRIES: usize = 10;
+pub const MAX_NAME_LEN: usize = 12;
+
+/// The settings a run was played with. Only runs on the same board are
+/// ranked against each other.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    pub cols: i32,
+    pub rows: i32,
+    pub wrap: bool,
+    /// The built-in layout's name or the level file's path.
+    pub level: String,
+    pub difficulty: Difficulty,
+}
+
+/// One finished run in the high-score table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub length: usize,
+    pub duration_secs: u64,
+    /// Seconds since the Unix epoch when the run ended.
+    pub timestamp: u64,
+    pub board: Board,
+    pub seed: u64,
+}
+
+impl HighScore {
+    /// The day the run ended, as `YYYY-MM-DD` (UTC).
+    pub fn date(&self) -> String {
+        // Days-to-civil conversion from Howard Hinnant's date algorithms.
+        let z = (self.timestamp / 86_400) as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Keeps `name` printable and short enough for the table.
+pub fn clean_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LEN)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// The best runs on each board, highest score first.
+///
+/// Stored as one tab-separated line per entry; lines that do not parse are
+/// dropped rather than failing the whole table. Lines from before the
+/// board was recorded in full are kept, but match no board.
+#[derive(Clone, Debug, Default)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub fn new() -> HighScores {
+        HighScores::default()
+    }
+    pub fn parse(text: &str) -> HighScores {
+        let mut table = HighScores::new();
+        for line in text.lines() {
+            if let Some(entry) = parse_line(line) {
+                table.insert(entry);
+            }
+        }
+        table
+    }
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for e in &self.entries {
+            let b = &e.board;
+            write!(
+                text,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                e.name, e.score, e.length, e.duration_secs, e.timestamp, b.cols, b.rows, e.seed
+            )
+            .unwrap();
+            if b.level.is_empty() {
+                text.push('\n');
+            } else {
+                let difficulty = b.difficulty.to_string().to_lowercase();
+                writeln!(text, "\t{}\t{}\t{}", b.wrap, b.level, difficulty).unwrap();
+            }
+        }
+        text
+    }
+    /// The runs on `board`, best first.
+    pub fn entries(&self, board: &Board) -> Vec<&HighScore> {
+        self.entries.iter().filter(|e| e.board == *board).collect()
+    }
+    /// Whether a run on `board` scoring `score` would make it into the
+    /// table.
+    pub fn qualifies(&self, board: &Board, score: u32) -> bool {
+        let entries = self.entries(board);
+        score > 0
+            && (entries.len() < MAX_ENTRIES
+                || entries.last().is_some_and(|last| score > last.score))
+    }
+    /// Adds `entry` in score order, returning its rank (0 is best) among
+    /// the runs on its board if it stayed in the table.
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self
+            .entries(&entry.board)
+            .iter()
+            .position(|e| entry.score > e.score)
+            .unwrap_or_else(|| self.entries(&entry.board).len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        let at = self
+            .entries
+            .iter()
+            .position(|e| entry.score > e.score)
+            .unwrap_or(self.entries.len());
+        let board = entry.board.clone();
+        self.entries.insert(at, entry);
+        // Drop whatever this board's run pushed off the bottom.
+        let mut kept = 0;
+        self.entries.retain(|e| {
+            if e.board != board {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_ENTRIES
+        });
+        Some(rank)
+    }
+}
+
+fn parse_line(line: &str) -> Option<HighScore> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let (wrap, level, difficulty) = match fields.len() {
+        // Written before wrap, level and difficulty were kept.
+        8 => (false, String::new(), Difficulty::Normal),
+        11 => (
+            fields[8].parse().ok()?,
+            fields[9].to_string(),
+            fields[10].parse().ok()?,
+        ),
+        _ => return None,
+    };
+    Some(HighScore {
+        name: clean_name(fields[0]),
+        score: fields[1].parse().ok()?,
+        length: fields[2].parse().ok()?,
+        duration_secs: fields[3].parse().ok()?,
+        timestamp: fields[4].parse().ok()?,
+        board: Board {
+            cols: fields[5].parse().ok()?,
+            rows: fields[6].parse().ok()?,
+            wrap,
+            level,
+            difficulty,
+        },
+        seed: fields[7].parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(level: &str) -> Board {
+        Board {
+            cols: 20,
+            rows: 15,
+            wrap: false,
+            level: level.to_string(),
+            difficulty: Difficulty::Normal,
+        }
+    }
+
+    fn run(name: &str, score: u32, level: &str) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score,
+            length: score as usize + 2,
+            duration_secs: 60,
+            timestamp: 1_700_000_000,
+            board: board(level),
+            seed: 42,
+        }
+    }
+
+    #[test]
+    fn insert_keeps_each_board_in_score_order() {
+        let mut table = HighScores::new();
+        assert_eq!(table.insert(run("a", 5, "box")), Some(0));
+        assert_eq!(table.insert(run("b", 9, "box")), Some(0));
+        assert_eq!(table.insert(run("c", 7, "box")), Some(1));
+        assert_eq!(table.insert(run("d", 1, "empty")), Some(0));
+        let names: Vec<&str> = table
+            .entries(&board("box"))
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["b", "c", "a"]);
+        assert_eq!(table.entries(&board("empty")).len(), 1);
+        assert!(table.entries(&board("cross")).is_empty());
+    }
+
+    #[test]
+    fn a_full_board_drops_its_lowest_run_only() {
+        let mut table = HighScores::new();
+        table.insert(run("other", 1, "empty"));
+        for score in 1..=MAX_ENTRIES as u32 {
+            table.insert(run("x", score * 10, "box"));
+        }
+        assert!(table.qualifies(&board("box"), 11));
+        assert!(!table.qualifies(&board("box"), 10));
+        assert_eq!(table.insert(run("low", 5, "box")), None);
+        assert_eq!(table.insert(run("new", 15, "box")), Some(MAX_ENTRIES - 1));
+        let entries = table.entries(&board("box"));
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries.last().unwrap().name, "new");
+        assert_eq!(table.entries(&board("empty")).len(), 1);
+    }
+
+    #[test]
+    fn qualifies_needs_a_score() {
+        let table = HighScores::new();
+        assert!(!table.qualifies(&board("box"), 0));
+        assert!(table.qualifies(&board("box"), 1));
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let mut table = HighScores::new();
+        let mut wrapped = run("ann", 12, "levels/spiral.txt");
+        wrapped.board.wrap = true;
+        wrapped.board.difficulty = Difficulty::Insane;
+        table.insert(wrapped.clone());
+        table.insert(run("bob", 3, "box"));
+        let text = table.to_text();
+        let parsed = HighScores::parse(&text);
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.entries(&wrapped.board), vec![&wrapped]);
+    }
+
+    #[test]
+    fn old_and_broken_lines() {
+        let text = "old\t4\t6\t30\t1600000000\t80\t60\t9\nbad line\nnew\tx\t1\t1\t1\t1\t1\t1\tfalse\tbox\tnormal\n";
+        let table = HighScores::parse(text);
+        assert!(table.entries(&board("box")).is_empty());
+        // The old run matches no board, but is kept when the table is saved.
+        assert_eq!(table.to_text(), "old\t4\t6\t30\t1600000000\t80\t60\t9\n");
+    }
+
+    #[test]
+    fn names_are_cleaned_and_dates_shown() {
+        assert_eq!(clean_name("a\tvery long name indeed"), "avery long n");
+        assert_eq!(run("a", 1, "box").date(), "2023-11-14");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::controller::{Controller, Snapshot};
+use crate::game::Direction;
+use std::collections::VecDeque;
+
+/// How many turns can be queued ahead of the snake.
+pub const INPUT_QUEUE_LEN: usize = 3;
+
+/// Direction changes pressed between ticks, consumed one per tick.
+///
+/// Keys are only checked against the direction the snake actually moved when
+/// they are taken off the queue, so a quick Up-then-Left is two turns rather
+/// than a lost turn or a reversal into the snake's own neck.
+#[derive(Default)]
+pub struct InputQueue {
+    pending: VecDeque<Direction>,
+}
+
+impl InputQueue {
+    pub fn new() -> InputQueue {
+        InputQueue::default()
+    }
+    /// Queues `dir` unless it repeats the last queued direction or the queue
+    /// is already full.
+    pub fn push(&mut self, dir: Direction) {
+        if self.pending.back() != Some(&dir) && self.pending.len() < INPUT_QUEUE_LEN {
+            self.pending.push_back(dir);
+        }
+    }
+    /// The direction a snake moving `moving` will be heading once every
+    /// queued turn is taken. Relative turns are made from here.
+    pub fn last(&self, moving: Direction) -> Direction {
+        self.pending.back().copied().unwrap_or(moving)
+    }
+    /// The next queued turn that is legal for a snake that last moved
+    /// `moving`. Turns that would be no-ops or reversals are dropped.
+    pub fn next(&mut self, moving: Direction) -> Option<Direction> {
+        while let Some(dir) = self.pending.pop_front() {
+            if dir != moving && dir != !moving {
+                return Some(dir);
+            }
+        }
+        None
+    }
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// The keyboard, as a controller: each tick takes the next queued turn.
+impl Controller for InputQueue {
+    fn next_move(&mut self, snapshot: &Snapshot) -> Option<Direction> {
+        self.next(snapshot.snake().curr_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn up_then_left_is_two_turns() {
+        let mut queue = InputQueue::new();
+        queue.push(Direction::Up);
+        queue.push(Direction::Left);
+        assert_eq!(queue.next(Direction::Right), Some(Direction::Up));
+        assert_eq!(queue.next(Direction::Up), Some(Direction::Left));
+        assert_eq!(queue.next(Direction::Left), None);
+    }
+
+    #[test]
+    fn reversals_and_repeats_are_dropped() {
+        let mut queue = InputQueue::new();
+        queue.push(Direction::Left);
+        queue.push(Direction::Left);
+        queue.push(Direction::Right);
+        assert_eq!(queue.next(Direction::Right), None);
+    }
+
+    #[test]
+    fn the_queue_holds_a_few_turns_at_most() {
+        let mut queue = InputQueue::new();
+        for &dir in &[
+            Direction::Up,
+            Direction::Left,
+            Direction::Down,
+            Direction::Right,
+        ] {
+            queue.push(dir);
+        }
+        assert_eq!(queue.last(Direction::Right), Direction::Down);
+        queue.clear();
+        assert_eq!(queue.last(Direction::Right), Direction::Right);
+    }
+}
@@ -0,0 +1,498 @@
+use crate::config::MIN_BOARD_SIDE;
+use crate::game::{Bounds, Direction, GridPos};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// What a player has to do to clear a level.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Goal {
+    Apples(u32),
+    Length(usize),
+    /// Stay alive this many seconds.
+    Survive(u64),
+}
+
+impl FromStr for Goal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Goal, String> {
+        let mut words = s.split_whitespace();
+        let kind = words.next().unwrap_or("");
+        let count = words.next().unwrap_or("");
+        let goal = match kind {
+            "apples" => count.parse().ok().map(Goal::Apples),
+            "length" => count.parse().ok().map(Goal::Length),
+            "time" => count.parse().ok().map(Goal::Survive),
+            _ => None,
+        };
+        match (goal, words.next()) {
+            (Some(goal), None) => Ok(goal),
+            _ => Err(format!(
+                "expected `apples <n>`, `length <n>` or `time <seconds>`, found {:?}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Goal::Apples(n) => write!(f, "apples {}", n),
+            Goal::Length(n) => write!(f, "length {}", n),
+            Goal::Survive(secs) => write!(f, "time {}", secs),
+        }
+    }
+}
+
+/// Everything fixed about a board before play starts: its size, the wall
+/// cells and where the snake starts.
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    pub bounds: Bounds,
+    pub walls: Vec<GridPos>,
+    pub start: GridPos,
+    pub start_dir: Direction,
+    /// Cells the first apples appear on, in order, before placement turns
+    /// random.
+    pub apples: Vec<GridPos>,
+    /// Milliseconds per tick at the start, if the level overrides the
+    /// difficulty's start speed.
+    pub speed: Option<u64>,
+    pub goal: Option<Goal>,
+}
+
+impl Level {
+    /// An open board with the snake in the middle, heading right.
+    pub fn empty(bounds: Bounds) -> Level {
+        Level {
+            name: String::new(),
+            bounds,
+            walls: Vec::new(),
+            start: bounds.middle(),
+            start_dir: Direction::Right,
+            apples: Vec::new(),
+            speed: None,
+            goal: None,
+        }
+    }
+
+    /// Reads a level from its text form.
+    ///
+    /// A header of `key = value` lines (`name`, `size = <cols>x<rows>`,
+    /// `speed = <ms per tick>`, `direction = up|down|left|right` and
+    /// `win = apples <n>|length <n>|time <seconds>`) is followed by a line
+    /// holding `---` and then the map, one text row per board row: `#` is a
+    /// wall, `.` floor, `S` the snake's head and `A` a fixed apple. `#`
+    /// comments are allowed in the header only, and blank lines anywhere but
+    /// between map rows.
+    pub fn parse(text: &str) -> Result<Level, LevelError> {
+        let mut lines = text.lines().enumerate().map(|(n, line)| (n + 1, line));
+        let mut level = Level::empty(Bounds::new(0, 0, false));
+        let mut size = None;
+
+        let mut map_start = None;
+        for (n, line) in &mut lines {
+            let trimmed = line.trim();
+            if trimmed == "---" {
+                map_start = Some(n + 1);
+                break;
+            }
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let (key, value, value_col) = match line.split_once('=') {
+                Some((key, value)) => {
+                    let indent = value.len() - value.trim_start().len();
+                    let col = line[..key.len() + 1 + indent].chars().count() + 1;
+                    (key.trim(), value.trim(), col)
+                }
+                None => return Err(LevelError::new(n, 1, "expected `key = value` or `---`")),
+            };
+            let invalid = |message: String| LevelError::new(n, value_col, message);
+            match key {
+                "name" => level.name = value.to_string(),
+                "size" => {
+                    let dims = value
+                        .split_once('x')
+                        .and_then(|(c, r)| Some((c.trim().parse().ok()?, r.trim().parse().ok()?)));
+                    match dims {
+                        Some(dims) => size = Some(dims),
+                        None => {
+                            return Err(invalid(format!(
+                                "expected `<cols>x<rows>`, found {:?}",
+                                value
+                            )))
+                        }
+                    }
+                }
+                "speed" => match value.parse() {
+                    Ok(ms) if ms >= 1 => level.speed = Some(ms),
+                    _ => {
+                        return Err(invalid(format!(
+                            "expected milliseconds per tick, at least 1, found {:?}",
+                            value
+                        )))
+                    }
+                },
+                "direction" => level.start_dir = value.parse().map_err(invalid)?,
+                "win" => level.goal = Some(value.parse().map_err(invalid)?),
+                _ => return Err(LevelError::new(n, 1, format!("unknown header {:?}", key))),
+            }
+        }
+        let map_start = match map_start {
+            Some(n) => n,
+            None => {
+                return Err(LevelError::new(
+                    text.lines().count().max(1),
+                    1,
+                    "missing `---` before the map",
+                ))
+            }
+        };
+
+        let blank = |&(_, line): &(usize, &str)| line.trim().is_empty();
+        let mut rows: Vec<(usize, &str)> = lines.skip_while(blank).collect();
+        while rows.last().is_some_and(blank) {
+            rows.pop();
+        }
+        if let Some(&(n, _)) = rows.iter().find(|row| blank(row)) {
+            return Err(LevelError::new(n, 1, "blank line inside the map"));
+        }
+        let map_start = rows.first().map_or(map_start, |&(n, _)| n);
+        let cols = rows.first().map_or(0, |(_, line)| line.chars().count());
+        let (cols, row_count) = (cols as i32, rows.len() as i32);
+        if let Some((want_cols, want_rows)) = size {
+            if (want_cols, want_rows) != (cols, row_count) {
+                return Err(LevelError::new(
+                    map_start,
+                    1,
+                    format!(
+                        "map is {}x{} but the header says {}x{}",
+                        cols, row_count, want_cols, want_rows
+                    ),
+                ));
+            }
+        }
+        if cols < MIN_BOARD_SIDE || row_count < MIN_BOARD_SIDE {
+            return Err(LevelError::new(
+                map_start,
+                1,
+                format!("map must be at least {0}x{0} cells", MIN_BOARD_SIDE),
+            ));
+        }
+        level.bounds = Bounds::new(cols, row_count, false);
+
+        let mut start = None;
+        for (row, &(n, line)) in rows.iter().enumerate() {
+            if line.chars().count() as i32 != cols {
+                return Err(LevelError::new(
+                    n,
+                    1,
+                    format!(
+                        "row is {} cells wide, expected {}",
+                        line.chars().count(),
+                        cols
+                    ),
+                ));
+            }
+            for (col, c) in line.chars().enumerate() {
+                let pos = GridPos::new(col as i32, row as i32);
+                match c {
+                    '#' => level.walls.push(pos),
+                    '.' => {}
+                    'A' => level.apples.push(pos),
+                    'S' if start.is_none() => start = Some(pos),
+                    'S' => return Err(LevelError::new(n, col + 1, "more than one `S`")),
+                    _ => return Err(LevelError::new(n, col + 1, format!("unexpected {:?}", c))),
+                }
+            }
+        }
+        level.start = match start {
+            Some(start) => start,
+            None => return Err(LevelError::new(map_start, 1, "map has no `S`")),
+        };
+        let tail = level.bounds.step(level.start, !level.start_dir);
+        if !level.bounds.contains(tail) || level.walls.contains(&tail) {
+            return Err(LevelError::new(
+                rows[level.start.row as usize].0,
+                level.start.col as usize + 1,
+                "no room behind `S` for the snake's tail",
+            ));
+        }
+        // Two of the open cells are taken by the snake itself.
+        let open = (cols * row_count) as usize - level.walls.len();
+        if open < 3 {
+            return Err(LevelError::new(
+                map_start,
+                1,
+                "map has no free cell for an apple",
+            ));
+        }
+        Ok(level)
+    }
+
+    /// The text form `parse` reads back. Wrapping is not part of it, since
+    /// level files leave that to the config.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        if !self.name.is_empty() {
+            writeln!(text, "name = {}", self.name).unwrap();
+        }
+        writeln!(text, "size = {}x{}", self.bounds.cols, self.bounds.rows).unwrap();
+        writeln!(text, "direction = {}", self.start_dir).unwrap();
+        if let Some(speed) = self.speed {
+            writeln!(text, "speed = {}", speed).unwrap();
+        }
+        if let Some(goal) = self.goal {
+            writeln!(text, "win = {}", goal).unwrap();
+        }
+        text.push_str("---\n");
+        for row in 0..self.bounds.rows {
+            for col in 0..self.bounds.cols {
+                let pos = GridPos::new(col, row);
+                text.push(if pos == self.start {
+                    'S'
+                } else if self.walls.contains(&pos) {
+                    '#'
+                } else if self.apples.contains(&pos) {
+                    'A'
+                } else {
+                    '.'
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+}
+
+/// A problem in a level file, with the 1-based line and column it was
+/// found at.
+#[derive(Debug)]
+pub struct LevelError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl LevelError {
+    fn new<S: Into<String>>(line: usize, col: usize, message: S) -> LevelError {
+        LevelError {
+            line,
+            col,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.col, self.message
+        )
+    }
+}
+
+impl Error for LevelError {}
+
+/// The layouts that ship with the game, selected with `layout = <name>`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BuiltinLevel {
+    Empty,
+    /// A wall around the edge of the board.
+    Box,
+    /// Two crossing walls through the middle, open at the edges.
+    Cross,
+    /// A boxed board split into serpentine corridors.
+    Corridors,
+}
+
+impl FromStr for BuiltinLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BuiltinLevel, String> {
+        match s {
+            "empty" => Ok(BuiltinLevel::Empty),
+            "box" => Ok(BuiltinLevel::Box),
+            "cross" => Ok(BuiltinLevel::Cross),
+            "corridors" => Ok(BuiltinLevel::Corridors),
+            _ => Err(format!("unknown layout {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for BuiltinLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BuiltinLevel::Empty => "empty",
+            BuiltinLevel::Box => "box",
+            BuiltinLevel::Cross => "cross",
+            BuiltinLevel::Corridors => "corridors",
+        })
+    }
+}
+
+fn border(bounds: &Bounds, walls: &mut Vec<GridPos>) {
+    for col in 0..bounds.cols {
+        walls.push(GridPos::new(col, 0));
+        walls.push(GridPos::new(col, bounds.rows - 1));
+    }
+    for row in 1..bounds.rows - 1 {
+        walls.push(GridPos::new(0, row));
+        walls.push(GridPos::new(bounds.cols - 1, row));
+    }
+}
+
+impl BuiltinLevel {
+    /// Lays this level out on a board of the given size. Any board of at
+    /// least `MIN_BOARD_SIDE` cells a side leaves room for the snake.
+    pub fn build(self, bounds: Bounds) -> Level {
+        let mut level = Level::empty(bounds);
+        let (cols, rows) = (bounds.cols, bounds.rows);
+        match self {
+            BuiltinLevel::Empty => {}
+            BuiltinLevel::Box => border(&bounds, &mut level.walls),
+            BuiltinLevel::Cross => {
+                for col in cols / 3..cols - cols / 3 {
+                    level.walls.push(GridPos::new(col, rows / 2));
+                }
+                for row in rows / 3..rows - rows / 3 {
+                    if row != rows / 2 {
+                        level.walls.push(GridPos::new(cols / 2, row));
+                    }
+                }
+                level.start = GridPos::new((cols / 6).max(1), rows / 6);
+            }
+            BuiltinLevel::Corridors => {
+                border(&bounds, &mut level.walls);
+                // Every fourth row is a wall with a two-cell gap at
+                // alternating ends.
+                for (i, row) in (4..rows - 2).step_by(4).enumerate() {
+                    let (from, to) = if i % 2 == 0 {
+                        (1, cols - 3)
+                    } else {
+                        (3, cols - 1)
+                    };
+                    for col in from..to {
+                        level.walls.push(GridPos::new(col, row));
+                    }
+                }
+                level.start = GridPos::new(2, 2);
+            }
+        }
+        level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_layouts_leave_room_for_the_snake() {
+        for &layout in &["empty", "box", "cross", "corridors"] {
+            let layout: BuiltinLevel = layout.parse().unwrap();
+            for &(cols, rows) in &[(4, 4), (5, 7), (80, 60)] {
+                let level = layout.build(Bounds::new(cols, rows, false));
+                let tail = level.bounds.step(level.start, !level.start_dir);
+                let fits = |cell| level.bounds.contains(cell) && !level.walls.contains(&cell);
+                assert!(
+                    fits(level.start) && fits(tail),
+                    "{} on {}x{}",
+                    layout,
+                    cols,
+                    rows
+                );
+            }
+        }
+        assert!("maze".parse::<BuiltinLevel>().is_err());
+    }
+
+    /// Where `Level::parse` says `text` goes wrong, as (line, column).
+    fn error_at(text: &str) -> (usize, usize) {
+        let e = Level::parse(text).unwrap_err();
+        (e.line, e.col)
+    }
+
+    #[test]
+    fn parses_header_and_map() {
+        let text = "name = Test\nspeed = 90\ndirection = up\nwin = apples 5\n---\n\
+                    ####\n#.A#\n#S.#\n#..#\n####\n";
+        let level = Level::parse(text).unwrap();
+        assert_eq!(level.name, "Test");
+        assert_eq!((level.bounds.cols, level.bounds.rows), (4, 5));
+        assert_eq!(level.start, GridPos::new(1, 2));
+        assert_eq!(level.start_dir, Direction::Up);
+        assert_eq!(level.apples, vec![GridPos::new(2, 1)]);
+        assert_eq!(level.speed, Some(90));
+        assert_eq!(level.goal, Some(Goal::Apples(5)));
+        assert_eq!(level.walls.len(), 14);
+        let again = Level::parse(&level.to_text()).unwrap();
+        assert_eq!(again.to_text(), level.to_text());
+    }
+
+    #[test]
+    fn header_errors_point_at_the_value() {
+        assert_eq!(error_at("speed = fast\n---\n"), (1, 9));
+        assert_eq!(error_at("# comment\nspeed = 0\n---\n"), (2, 9));
+        assert_eq!(error_at("direction =  sideways\n---\n"), (1, 14));
+        assert_eq!(error_at("\ncolour = red\n---\n"), (2, 1));
+        assert_eq!(error_at("name = x\n"), (1, 1));
+    }
+
+    #[test]
+    fn map_errors_point_at_the_cell() {
+        assert_eq!(error_at("---\n....\n.S..\n..x.\n....\n"), (4, 3));
+        assert_eq!(error_at("---\n....\n.S..\n...\n....\n"), (4, 1));
+        assert_eq!(error_at("---\n.S..\n....\n...S\n....\n"), (4, 4));
+        assert_eq!(error_at("---\n....\n....\n....\n....\n"), (2, 1));
+        assert_eq!(
+            error_at("size = 5x4\n---\n....\n.S..\n....\n....\n"),
+            (3, 1)
+        );
+        assert_eq!(error_at("---\n...\n.S.\n...\n"), (2, 1));
+    }
+
+    #[test]
+    fn the_snake_and_an_apple_need_room() {
+        assert_eq!(error_at("---\n....\nS...\n....\n....\n"), (3, 1));
+        assert_eq!(
+            error_at("direction = left\n---\n####\n#S.#\n####\n####\n"),
+            (3, 1)
+        );
+    }
+
+    #[test]
+    fn blank_lines_only_surround_the_map() {
+        let level = Level::parse("---\n\n\n....\n.S..\n....\n....\n\n  \n").unwrap();
+        assert_eq!((level.bounds.cols, level.bounds.rows), (4, 4));
+        assert_eq!(level.start, GridPos::new(1, 1));
+        assert_eq!(error_at("---\n....\n.S..\n\n....\n....\n"), (4, 1));
+        assert_eq!(error_at("---\n\n...\n.S..\n....\n....\n"), (3, 1));
+    }
+
+    #[test]
+    fn the_value_column_starts_after_the_equals_sign() {
+        assert_eq!(error_at("direction = i\n---\n"), (1, 13));
+        assert_eq!(error_at("speed=speed\n---\n"), (1, 7));
+        assert_eq!(error_at("  win =\tapples\n---\n"), (1, 9));
+    }
+
+    #[test]
+    fn goals_out_of_range_are_rejected() {
+        assert_eq!("apples 4294967295".parse(), Ok(Goal::Apples(u32::MAX)));
+        assert!("apples 4294967297".parse::<Goal>().is_err());
+        assert!("length 18446744073709551616".parse::<Goal>().is_err());
+        assert!("time -1".parse::<Goal>().is_err());
+        assert!("apples 3 more".parse::<Goal>().is_err());
+        for goal in &[Goal::Apples(7), Goal::Length(20), Goal::Survive(90)] {
+            assert_eq!(goal.to_string().parse(), Ok(*goal));
+        }
+    }
+}
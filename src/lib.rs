@@ -0,0 +1,23 @@
+//! Game rules for snake, free of any windowing or audio dependency.
+//!
+//! The `snake` binary drives a `Game` from ggez; everything in here can be
+//! built and exercised with `--no-default-features`.
+
+pub mod ai;
+pub mod bindings;
+pub mod campaign;
+pub mod config;
+pub mod controller;
+pub mod difficulty;
+pub mod env;
+pub mod external;
+pub mod free_cells;
+pub mod game;
+pub mod highscores;
+pub mod input;
+pub mod level;
+pub mod replay;
+pub mod sim;
+pub mod timestep;
+pub mod tournament;
+pub mod versus;
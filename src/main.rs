@@ -1,309 +1,1069 @@
 use ggez::audio;
 use ggez::conf;
 use ggez::event;
-use ggez::event::{Keycode, Mod};
+use ggez::event::{Axis, Button, Keycode, Mod};
 use ggez::graphics;
 use ggez::graphics::{DrawMode, Point2};
-use ggez::{Context, GameResult};
-use rand::Rng;
+use ggez::{Context, GameError, GameResult};
+use snake::bindings::{Action, Bindings, ControlMode};
+use snake::campaign::{self, Campaign};
+use snake::config::{resource_path, Config};
+use snake::controller::{Controller, Snapshot};
+use snake::external::BotSpec;
+use snake::game::{Apple, Bounds, Direction, Event, Game, GridPos, Outcome, Snake};
+use snake::highscores::{self, Board, HighScore, HighScores};
+use snake::input::InputQueue;
+use snake::level::{Goal, Level};
+use snake::replay::Replay;
+use snake::timestep::FixedTimestep;
+use snake::versus::Match;
 use std::collections::VecDeque;
 use std::env;
-use std::ops::Not;
-use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
-
-const BOARD_WIDTH: u32 = 800;
-const BOARD_HEIGHT: u32 = 600;
-const CELL_RADIUS: u32 = 5;
-const CELL_DIAMETER: u32 = 2 * CELL_RADIUS;
-const SLOW_SPEED: u64 = 125;
+use std::fs;
+use std::io;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
 const FAST_SPEED: u64 = 25;
+/// How far a stick has to be pushed, out of `i16::MAX`, to steer.
+const STICK_DEADZONE: i16 = 16_000;
+const HIGH_SCORES_FILE: &str = "highscores.txt";
+const PROGRESS_FILE: &str = "progress.txt";
+const CAMPAIGN_LIST: &str = "/levels/campaign.txt";
+/// The last game played, kept in the user data directory.
+const REPLAY_FILE: &str = "last-replay.txt";
+/// Playback speeds a replay can be watched at, as multiples of real time.
+const REPLAY_SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+/// One colour per snake, in player order.
+const SNAKE_COLORS: [(f32, f32, f32); 4] = [
+    (1.0, 1.0, 1.0),
+    (0.3, 0.7, 1.0),
+    (1.0, 0.8, 0.2),
+    (0.8, 0.4, 1.0),
+];
 
-// TODO
-// Buffer unique keyinput
+/// Pixel geometry of the window, derived from the board size and the
+/// configured cell size.
+#[derive(Copy, Clone)]
+struct Layout {
+    cell_size: f32,
+    width: f32,
+    height: f32,
+}
 
-trait Locate {
-    fn cartesian(&self) -> (f32, f32);
-    fn dist_to<T: Locate>(&self, other: &T) -> f32 {
-        let (x1, y1) = self.cartesian();
-        let (x2, y2) = other.cartesian();
-        (x2 - x1).hypot(y2 - y1)
+impl Layout {
+    fn new(bounds: &Bounds, cell_size: u32) -> Layout {
+        let cell_size = cell_size as f32;
+        Layout {
+            cell_size,
+            width: bounds.cols as f32 * cell_size,
+            height: bounds.rows as f32 * cell_size,
+        }
+    }
+    fn cell_rect(&self, pos: GridPos) -> graphics::Rect {
+        graphics::Rect::new(
+            pos.col as f32 * self.cell_size,
+            pos.row as f32 * self.cell_size,
+            self.cell_size,
+            self.cell_size,
+        )
+    }
+    fn cell_center(&self, pos: GridPos) -> Point2 {
+        self.point(pos.col as f32, pos.row as f32)
+    }
+    /// Pixel centre of a possibly fractional cell position.
+    fn point(&self, col: f32, row: f32) -> Point2 {
+        Point2::new((col + 0.5) * self.cell_size, (row + 0.5) * self.cell_size)
     }
 }
 
-#[derive(Copy, Clone)]
-struct SnakeCell {
-    x: f32,
-    y: f32,
-    r: f32,
+trait Draw {
+    fn draw(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()>;
 }
 
-impl Locate for SnakeCell {
-    fn cartesian(&self) -> (f32, f32) {
-        (self.x, self.y)
+impl Draw for GridPos {
+    fn draw(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        graphics::circle(
+            ctx,
+            DrawMode::Fill,
+            layout.cell_center(*self),
+            layout.cell_size / 2.0,
+            0.1,
+        )
     }
 }
 
-impl Locate for Apple {
-    fn cartesian(&self) -> (f32, f32) {
-        (self.x, self.y)
+impl Draw for Apple {
+    fn draw(&self, ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+        graphics::set_color(ctx, graphics::Color::new(1.0, 0.0, 0.0, 1.0))?;
+        self.pos.draw(ctx, layout)
     }
 }
 
-#[derive(Copy, Clone)]
-struct Apple {
-    x: f32,
-    y: f32,
-    r: f32,
+fn draw_walls(ctx: &mut Context, layout: &Layout, walls: &[GridPos]) -> GameResult<()> {
+    graphics::set_color(ctx, graphics::Color::new(0.4, 0.4, 0.5, 1.0))?;
+    for &wall in walls {
+        graphics::rectangle(ctx, DrawMode::Fill, layout.cell_rect(wall))?;
+    }
+    Ok(())
 }
 
-struct Grid;
-
-impl Grid {
-    fn random_x() -> f32 {
-        let mut rng = rand::thread_rng();
-        let x = CELL_RADIUS + rng.gen_range(0, BOARD_WIDTH / CELL_DIAMETER) * CELL_DIAMETER;
-        x as f32
-    }
-    fn random_y() -> f32 {
-        let mut rng = rand::thread_rng();
-        let y = CELL_RADIUS + rng.gen_range(0, BOARD_HEIGHT / CELL_DIAMETER) * CELL_DIAMETER;
-        y as f32
-    }
-    fn middle_x() -> f32 {
-        let slots = BOARD_WIDTH / CELL_DIAMETER;
-        let x = CELL_RADIUS + (slots / 2) * CELL_DIAMETER;
-        x as f32
-    }
-    fn middle_y() -> f32 {
-        let slots = BOARD_HEIGHT / CELL_DIAMETER;
-        let y = CELL_RADIUS + (slots / 2) * CELL_DIAMETER;
-        y as f32
+/// Single-cell move from `from` to `to`, taking the short way across the
+/// seam when the board wraps.
+fn wrapped_delta(from: i32, to: i32, size: i32) -> i32 {
+    let delta = to - from;
+    if delta > 1 {
+        delta - size
+    } else if delta < -1 {
+        delta + size
+    } else {
+        delta
     }
 }
 
-impl Apple {
-    fn new() -> Apple {
-        Apple {
-            x: Grid::random_x(),
-            y: Grid::random_y(),
-            r: CELL_RADIUS as f32,
+/// Draws `snake` `alpha` of the way from where its cells were before the
+/// last tick (`prev`) to where they are now.
+fn draw_snake(
+    ctx: &mut Context,
+    layout: &Layout,
+    bounds: &Bounds,
+    snake: &Snake,
+    prev: &VecDeque<GridPos>,
+    alpha: f32,
+    color: (f32, f32, f32),
+) -> GameResult<()> {
+    graphics::set_color(ctx, graphics::Color::new(color.0, color.1, color.2, 1.0))?;
+    let (cols, rows) = (bounds.cols as f32, bounds.rows as f32);
+    for (i, &cell) in snake.body.iter().enumerate() {
+        let from = prev.get(i).copied().unwrap_or(cell);
+        let col = from.col as f32 + wrapped_delta(from.col, cell.col, bounds.cols) as f32 * alpha;
+        let row = from.row as f32 + wrapped_delta(from.row, cell.row, bounds.rows) as f32 * alpha;
+        // A cell sliding across the seam is half on each side.
+        let mut copies = vec![(col, row)];
+        if col < 0.0 {
+            copies.push((col + cols, row));
+        } else if col > cols - 1.0 {
+            copies.push((col - cols, row));
+        }
+        if row < 0.0 {
+            copies.push((col, row + rows));
+        } else if row > rows - 1.0 {
+            copies.push((col, row - rows));
+        }
+        for (col, row) in copies {
+            graphics::circle(
+                ctx,
+                DrawMode::Fill,
+                layout.point(col, row),
+                layout.cell_size / 2.0,
+                0.1,
+            )?;
         }
     }
-    fn eaten(&mut self) {
-        self.x = Grid::random_x();
-        self.y = Grid::random_y();
-    }
-    fn draw(&self, ctx: &mut Context) -> GameResult<()> {
-        graphics::set_color(ctx, graphics::Color::new(1.0, 0.0, 0.0, 1.0))?;
-        graphics::circle(
-            ctx,
-            DrawMode::Fill,
-            Point2::new(self.x, self.y),
-            self.r,
-            0.1,
-        )
+    Ok(())
+}
+
+struct ScoreBoard {
+    pos: graphics::Point2,
+    font: graphics::Font,
+    body_font: graphics::Font,
+    small_font: graphics::Font,
+}
+
+fn score_pos(layout: &Layout) -> graphics::Point2 {
+    graphics::Point2::new((layout.width - 200.0).max(10.0), 20.0)
+}
+
+fn goal_text(goal: Goal, done: u64, target: u64) -> String {
+    match goal {
+        Goal::Apples(_) => format!("Apples: {}/{}", done, target),
+        Goal::Length(_) => format!("Length: {}/{}", done, target),
+        Goal::Survive(_) => format!("Time: {}/{}s", done, target),
     }
 }
 
-impl SnakeCell {
-    fn new(x: f32, y: f32) -> SnakeCell {
-        SnakeCell {
-            x,
-            y,
-            r: CELL_RADIUS as f32,
+impl ScoreBoard {
+    fn new(ctx: &mut Context, layout: &Layout) -> ScoreBoard {
+        ScoreBoard {
+            pos: score_pos(layout),
+            font: graphics::Font::new(ctx, "/DejaVuSerif.ttf", 24).unwrap(),
+            body_font: graphics::Font::new(ctx, "/DejaVuSerif.ttf", 16).unwrap(),
+            small_font: graphics::Font::new(ctx, "/DejaVuSerif.ttf", 10).unwrap(),
         }
     }
-    fn next_to(&self, dir: Direction) -> SnakeCell {
-        match dir {
-            Direction::Up => SnakeCell::new(self.x, self.y - CELL_DIAMETER as f32),
-            Direction::Down => SnakeCell::new(self.x, self.y + CELL_DIAMETER as f32),
-            Direction::Left => SnakeCell::new(self.x - CELL_DIAMETER as f32, self.y),
-            Direction::Right => SnakeCell::new(self.x + CELL_DIAMETER as f32, self.y),
+    fn relayout(&mut self, layout: &Layout) {
+        self.pos = score_pos(layout);
+    }
+    fn draw(&self, ctx: &mut Context, game: &Game, humans: usize) -> GameResult<()> {
+        let score_text = match game.players.len() {
+            1 => format!("Score: {}", game.players[0].score.val),
+            _ => {
+                let scores: Vec<String> = game
+                    .players
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| format!("{}: {}", short_name(i, humans), p.score.val))
+                    .collect();
+                scores.join("  ")
+            }
+        };
+        let text = graphics::Text::new(ctx, score_text.as_str(), &self.font)?;
+        graphics::draw(ctx, &text, self.pos, 0.0)?;
+        let seed_text = format!("Seed: {}", game.seed());
+        let text = graphics::Text::new(ctx, seed_text.as_str(), &self.small_font)?;
+        let seed_pos = graphics::Point2::new(self.pos.x, self.pos.y + 30.0);
+        graphics::draw(ctx, &text, seed_pos, 0.0)?;
+        let speed_text = format!(
+            "{}: {} ms per move",
+            game.difficulty(),
+            game.tick_interval()
+        );
+        let text = graphics::Text::new(ctx, speed_text.as_str(), &self.small_font)?;
+        let speed_pos = graphics::Point2::new(self.pos.x, self.pos.y + 42.0);
+        graphics::draw(ctx, &text, speed_pos, 0.0)?;
+        if let (Some(goal), Some((done, target))) = (game.goal(), game.goal_progress(0)) {
+            let goal_text = goal_text(goal, done, target);
+            let text = graphics::Text::new(ctx, goal_text.as_str(), &self.body_font)?;
+            let goal_pos = graphics::Point2::new(self.pos.x, self.pos.y + 57.0);
+            graphics::draw(ctx, &text, goal_pos, 0.0)?;
         }
+        Ok(())
     }
-    fn draw(&self, ctx: &mut Context) -> GameResult<()> {
-        graphics::circle(
-            ctx,
-            DrawMode::Fill,
-            Point2::new(self.x, self.y),
-            self.r,
-            0.1,
-        )
+    /// Draws one line of small print along the bottom of the window.
+    fn draw_status(&self, ctx: &mut Context, line: &str) -> GameResult<()> {
+        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+        let text = graphics::Text::new(ctx, line, &self.body_font)?;
+        let (_, height) = graphics::get_size(ctx);
+        let pos = graphics::Point2::new(10.0, height as f32 - text.height() as f32 - 6.0);
+        graphics::draw(ctx, &text, pos, 0.0)
+    }
+    /// Draws a heading and some lines of text centred in the window.
+    fn draw_centered(&self, ctx: &mut Context, heading: &str, lines: &[String]) -> GameResult<()> {
+        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+        let mut texts = vec![graphics::Text::new(ctx, heading, &self.font)?];
+        for line in lines {
+            texts.push(graphics::Text::new(ctx, line, &self.body_font)?);
+        }
+        let (width, height) = graphics::get_size(ctx);
+        let total: u32 = texts.iter().map(|text| text.height() + 8).sum();
+        let mut y = (height as f32 - total as f32) / 2.0;
+        for text in &texts {
+            let pos = graphics::Point2::new((width as f32 - text.width() as f32) / 2.0, y);
+            graphics::draw(ctx, text, pos, 0.0)?;
+            y += (text.height() + 8) as f32;
+        }
+        Ok(())
     }
 }
 
-struct Snake {
-    body: VecDeque<SnakeCell>,
-    curr_dir: Direction,
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+enum Screen {
+    Title,
+    Playing,
+    Paused,
+    NameEntry,
+    LevelComplete,
+    /// A versus round has ended; the match may have too.
+    RoundOver,
+    GameOver,
+    /// Watching a recorded game.
+    Replay,
 }
 
-impl Not for Direction {
-    type Output = Direction;
+/// Where a replay being watched has got to.
+struct Playback {
+    replay: Replay,
+    /// The next tick to play.
+    tick: usize,
+    /// Index into `REPLAY_SPEEDS`.
+    speed: usize,
+    paused: bool,
+}
 
-    fn not(self) -> Direction {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Down => Direction::Up,
-            Direction::Left => Direction::Right,
-            Direction::Right => Direction::Left,
-        }
+impl Playback {
+    fn is_finished(&self) -> bool {
+        self.tick >= self.replay.len()
     }
 }
 
-impl Snake {
-    fn new() -> Snake {
-        let head = SnakeCell::new(Grid::middle_x(), Grid::middle_y());
-        let body = vec![head, head.next_to(Direction::Left)]
-            .into_iter()
-            .collect();
-        Snake {
-            body,
-            curr_dir: Direction::Right,
-        }
+fn high_scores_path(ctx: &Context) -> PathBuf {
+    ctx.filesystem.get_user_data_dir().join(HIGH_SCORES_FILE)
+}
+
+fn load_high_scores(ctx: &Context) -> HighScores {
+    fs::read_to_string(high_scores_path(ctx))
+        .map(|text| HighScores::parse(&text))
+        .unwrap_or_default()
+}
+
+/// The index of the furthest campaign level unlocked so far.
+fn load_progress(ctx: &Context) -> usize {
+    let path = ctx.filesystem.get_user_data_dir().join(PROGRESS_FILE);
+    fs::read_to_string(path)
+        .map(|text| campaign::parse_progress(&text))
+        .unwrap_or(0)
+}
+
+fn save_progress(ctx: &Context, unlocked: usize) -> io::Result<()> {
+    let dir = ctx.filesystem.get_user_data_dir();
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(PROGRESS_FILE), campaign::progress_text(unlocked))
+}
+
+fn save_replay(ctx: &Context, replay: &Replay) -> io::Result<PathBuf> {
+    let dir = ctx.filesystem.get_user_data_dir();
+    fs::create_dir_all(dir)?;
+    let path = dir.join(REPLAY_FILE);
+    fs::write(&path, replay.to_text())?;
+    Ok(path)
+}
+
+fn save_high_scores(ctx: &Context, table: &HighScores) -> io::Result<()> {
+    let path = high_scores_path(ctx);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
     }
-    fn shorten_tail(&mut self) {
-        self.body.pop_back();
+    fs::write(path, table.to_text())
+}
+
+struct MainState {
+    config: Config,
+    level: Level,
+    campaign: Option<Campaign>,
+    /// Set when the last campaign level has just been cleared.
+    campaign_cleared: bool,
+    screen: Screen,
+    game: Game,
+    layout: Layout,
+    /// One queue per snake.
+    inputs: Vec<InputQueue>,
+    last_frame: Instant,
+    timestep: FixedTimestep,
+    prev_bodies: Vec<VecDeque<GridPos>>,
+    /// Round wins so far when more than one snake shares the board.
+    versus: Option<Match>,
+    /// Steering for the computer opponents, which come after the players.
+    bots: Vec<Box<dyn Controller>>,
+    /// Steers player one instead of the keyboard when `--bot` is given.
+    autopilot: Option<Box<dyn Controller>>,
+    round_winner: Option<usize>,
+    /// Every tick of the game being played, saved when it ends.
+    recording: Replay,
+    /// Set when `--replay` is given; the game is then driven from it.
+    playback: Option<Playback>,
+    since_key: Duration,
+    play_time: Duration,
+    background_music: audio::Source,
+    eating_sound: audio::Source,
+    game_over_sound: audio::Source,
+    score_board: ScoreBoard,
+    high_scores: HighScores,
+    player_name: String,
+    delay: u64,
+    /// Latest position of the gamepad's left stick, and the direction it
+    /// last steered.
+    stick: (i16, i16),
+    stick_dir: Option<Direction>,
+}
+
+fn background_music(ctx: &mut Context) -> audio::Source {
+    let mut music = audio::Source::new(ctx, "/crystals.ogg").unwrap();
+    music.set_volume(0.4);
+    music
+}
+
+/// "Player 2" or "CPU 1", for snake `i` when the first `humans` are people.
+fn snake_name(i: usize, humans: usize) -> String {
+    if i < humans {
+        format!("Player {}", i + 1)
+    } else {
+        format!("CPU {}", i - humans + 1)
     }
-    fn advance(&mut self) {
-        let new_head = self.head().next_to(self.curr_dir);
-        self.body.push_front(new_head);
+}
+
+fn short_name(i: usize, humans: usize) -> String {
+    if i < humans {
+        format!("P{}", i + 1)
+    } else {
+        format!("CPU{}", i - humans + 1)
     }
-    fn head(&self) -> SnakeCell {
-        *self.body.front().unwrap()
+}
+
+fn versus_match(config: &Config) -> Option<Match> {
+    if config.snakes() > 1 {
+        Some(Match::new(config.snakes(), config.rounds))
+    } else {
+        None
     }
-    fn draw(&self, ctx: &mut Context) -> GameResult<()> {
-        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
-        for cell in &self.body {
-            cell.draw(ctx)?;
-        }
-        Ok(())
+}
+
+fn new_game(config: &Config, level: &Level) -> Result<Game, String> {
+    let seed = config.seed.unwrap_or_else(rand::random);
+    Game::with_snakes(level, config.snakes(), config.difficulty, seed)
+}
+
+/// Reads a whole file through the resource filesystem. Paths are taken
+/// relative to the resources directory.
+fn read_resource(ctx: &mut Context, path: &str) -> Result<String, String> {
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{}", path)
+    };
+    let mut text = String::new();
+    ctx.filesystem
+        .open(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|mut file| file.read_to_string(&mut text).map_err(|e| e.to_string()))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    Ok(text)
+}
+
+fn read_level(ctx: &mut Context, path: &str) -> Result<Level, String> {
+    let text = read_resource(ctx, path)?;
+    Level::parse(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// The level named by `--level`, or else the configured built-in layout.
+fn load_level(ctx: &mut Context, config: &Config) -> Result<Level, String> {
+    match config.level {
+        Some(ref path) => read_level(ctx, path),
+        None => Ok(config
+            .layout
+            .build(Bounds::new(config.cols, config.rows, false))),
     }
-    fn bounds_check(&self, bounds: &Bounds) -> bool {
-        self.body.iter().all(|cell| bounds.check(cell.cartesian()))
+}
+
+/// Every level listed in the campaign file, resuming at saved progress.
+fn load_campaign(ctx: &mut Context) -> Result<Campaign, String> {
+    let list = read_resource(ctx, CAMPAIGN_LIST)?;
+    let mut levels = Vec::new();
+    for path in campaign::parse_list(&list) {
+        levels.push(read_level(ctx, &path)?);
     }
-    fn body_check(&self) -> bool {
-        let mut collisions = 0;
-        for cell in &self.body {
-            if cell.dist_to(&self.head()) < CELL_RADIUS as f32 {
-                collisions += 1;
-            }
-        }
-        collisions <= 1
+    if levels.is_empty() {
+        return Err(format!("{}: no levels listed", CAMPAIGN_LIST));
     }
+    Ok(Campaign::new(levels, load_progress(ctx)))
 }
 
-struct Bounds {
-    width: f32,
-    height: f32,
+/// Joins parallel key lists into "A/B or C/D" groups, one group per key
+/// every list has.
+fn key_groups(lists: &[Vec<String>]) -> Option<String> {
+    let sets = lists.iter().map(Vec::len).min().unwrap_or(0);
+    if sets == 0 {
+        return None;
+    }
+    let groups: Vec<String> = (0..sets)
+        .map(|i| {
+            let group: Vec<&str> = lists.iter().map(|keys| keys[i].as_str()).collect();
+            group.join("/")
+        })
+        .collect();
+    Some(groups.join(" or "))
 }
 
-impl Bounds {
-    fn new(width: f32, height: f32) -> Bounds {
-        Bounds {
-            width,
-            height,
+/// A one-line summary of the keyboard bindings for the title screen, such
+/// as "Steer with UP/LEFT/DOWN/RIGHT or W/A/S/D, P to pause". Keys that
+/// `taken` also binds are left out, since those belong to the other player.
+fn controls_line(bindings: &Bindings, taken: Option<&Bindings>, mode: ControlMode) -> String {
+    let keys = |action| -> Vec<String> {
+        bindings
+            .keys_for(action)
+            .into_iter()
+            .filter(|key| !key.starts_with("pad:"))
+            .filter(|key| taken.and_then(|taken| taken.action(key)).is_none())
+            .map(str::to_uppercase)
+            .collect()
+    };
+    let left = keys(Action::Steer(Direction::Left));
+    let right = keys(Action::Steer(Direction::Right));
+    let mut turn_left = keys(Action::TurnLeft);
+    let mut turn_right = keys(Action::TurnRight);
+    let mut parts = Vec::new();
+    match mode {
+        ControlMode::Absolute => {
+            let up = keys(Action::Steer(Direction::Up));
+            let down = keys(Action::Steer(Direction::Down));
+            if let Some(groups) = key_groups(&[up, left, down, right]) {
+                parts.push(format!("steer with {}", groups));
+            }
         }
+        ControlMode::Relative => {
+            // Left and right keys turn, so they join the turn keys.
+            turn_left = left.into_iter().chain(turn_left).collect();
+            turn_right = right.into_iter().chain(turn_right).collect();
+        }
+    }
+    if let Some(groups) = key_groups(&[turn_left, turn_right]) {
+        parts.push(format!("turn with {}", groups));
     }
-    fn check(&self, coord: (f32, f32)) -> bool {
-        coord.0 < self.width && coord.0 > 0.0 && coord.1 < self.height && coord.1 > 0.0
+    if let Some(pause) = keys(Action::Pause).first() {
+        parts.push(format!("{} to pause", pause));
+    }
+    let line = parts.join(", ");
+    let mut chars = line.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => line,
     }
 }
 
-struct Score {
-    pos: graphics::Point2,
-    font: graphics::Font,
-    val: u32,
+fn level_title(campaign: &Campaign) -> String {
+    format!(
+        "Level {}/{}: {}",
+        campaign.current() + 1,
+        campaign.len(),
+        campaign.level().name
+    )
 }
 
-impl Score {
-    fn new(ctx: &mut Context) -> Score {
-        Score {
-            pos: graphics::Point2::new(600.0, 20.0),
-            font: graphics::Font::new(ctx, "/DejaVuSerif.ttf", 24).unwrap(),
-            val: 0,
-        }
-    }
-    fn draw(&self, ctx: &mut Context) -> GameResult<()> {
-        let score_text = format!("Score: {}", self.val);
-        let text = graphics::Text::new(ctx, score_text.as_str(), &self.font)?;
-        graphics::draw(ctx, &text, self.pos, 0.0)?;
-        Ok(())
-    }
-    fn increment(&mut self) {
-        self.val += 1;
+fn campaign_lines(campaign: &Campaign) -> Vec<String> {
+    let mut lines = vec![String::new(), level_title(campaign)];
+    if campaign.unlocked() > 0 {
+        lines.push("Left/Right to choose an unlocked level".to_string());
     }
+    lines
 }
 
-struct MainState {
-    snake: Snake,
-    apple: Apple,
-    bounds: Bounds,
-    last_move: Instant,
-    last_key_moment: Instant,
-    background_music: audio::Source,
-    eating_sound: audio::Source,
-    game_over_sound: audio::Source,
-    score: Score,
-    delay: u64,
-    game_over: bool,
+/// Resizes the window to fit `layout`, since level files set their own
+/// board size.
+fn fit_window(ctx: &mut Context, layout: &Layout) -> GameResult<()> {
+    let (width, height) = (layout.width as u32, layout.height as u32);
+    if graphics::get_size(ctx) != (width, height) {
+        graphics::set_resolution(ctx, width, height)?;
+        let screen = graphics::Rect::new(0.0, 0.0, layout.width, layout.height);
+        graphics::set_screen_coordinates(ctx, screen)?;
+    }
+    Ok(())
 }
 
 impl MainState {
-    fn new(ctx: &mut Context) -> GameResult<MainState> {
-        let mut background_music = audio::Source::new(ctx, "/crystals.ogg").unwrap();
-        background_music.set_volume(0.4);
-        background_music.play().unwrap();
+    fn new(
+        ctx: &mut Context,
+        config: Config,
+        mut level: Level,
+        campaign: Option<Campaign>,
+        replay: Option<Replay>,
+    ) -> GameResult<MainState> {
+        level.bounds.wrap = config.wrap;
+        let layout = Layout::new(&level.bounds, config.cell_size);
+        fit_window(ctx, &layout)?;
+        let game = match replay {
+            Some(ref replay) => replay.game(),
+            None => new_game(&config, &level).map_err(GameError::UnknownError)?,
+        };
+        let delay = game.tick_interval();
+        let time_limit = Duration::from_millis(config.move_time);
+        let start_bot = |spec: &BotSpec| {
+            spec.controller(time_limit)
+                .map_err(|e| GameError::UnknownError(e.to_string()))
+        };
+        // A replay is driven by its recorded moves, so it starts no bots.
+        let opponents = if replay.is_some() {
+            0
+        } else {
+            config.opponents
+        };
+        let bots = (0..opponents)
+            .map(|_| start_bot(&config.ai))
+            .collect::<GameResult<Vec<_>>>()?;
+        let autopilot = match config.bot {
+            Some(ref bot) if replay.is_none() => Some(start_bot(bot)?),
+            _ => None,
+        };
         let s = MainState {
-            snake: Snake::new(),
-            apple: Apple::new(),
-            bounds: Bounds::new(BOARD_WIDTH as f32, BOARD_HEIGHT as f32),
-            last_move: Instant::now(),
-            last_key_moment: Instant::now(),
-            background_music,
+            screen: if replay.is_some() {
+                Screen::Replay
+            } else {
+                Screen::Title
+            },
+            recording: Replay::new(&level, &game, config.players),
+            playback: replay.map(|replay| Playback {
+                replay,
+                tick: 0,
+                speed: REPLAY_SPEEDS.iter().position(|&s| s == 1.0).unwrap(),
+                paused: false,
+            }),
+            prev_bodies: game.players.iter().map(|p| p.snake.body.clone()).collect(),
+            game,
+            layout,
+            inputs: (0..config.players).map(|_| InputQueue::new()).collect(),
+            last_frame: Instant::now(),
+            timestep: FixedTimestep::new(Duration::from_millis(delay)),
+            versus: versus_match(&config),
+            bots,
+            autopilot,
+            round_winner: None,
+            since_key: Duration::from_secs(0),
+            play_time: Duration::from_secs(0),
+            background_music: background_music(ctx),
             eating_sound: audio::Source::new(ctx, "/gulp.ogg").unwrap(),
             game_over_sound: audio::Source::new(ctx, "/gameover.ogg").unwrap(),
-            score: Score::new(ctx),
-            delay: SLOW_SPEED,
-            game_over: false,
+            score_board: ScoreBoard::new(ctx, &layout),
+            high_scores: load_high_scores(ctx),
+            player_name: String::new(),
+            delay,
+            stick: (0, 0),
+            stick_dir: None,
+            config,
+            level,
+            campaign,
+            campaign_cleared: false,
         };
         Ok(s)
     }
+    /// Switches to the campaign's current level, resizing the window to fit.
+    fn load_campaign_level(&mut self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(ref campaign) = self.campaign {
+            self.level = campaign.level().clone();
+            self.level.bounds.wrap = self.config.wrap;
+            self.layout = Layout::new(&self.level.bounds, self.config.cell_size);
+            self.score_board.relayout(&self.layout);
+            fit_window(ctx, &self.layout)?;
+            self.game = new_game(&self.config, &self.level).map_err(GameError::UnknownError)?;
+        }
+        Ok(())
+    }
+    /// Moves the title screen's level choice among the unlocked levels.
+    fn select_level(&mut self, ctx: &mut Context, offset: isize) {
+        if let Some(ref mut campaign) = self.campaign {
+            campaign.select(offset);
+        }
+        self.load_campaign_level(ctx).unwrap();
+    }
+    /// Leaves the level-complete screen, either into the next level or,
+    /// after the last one, back to the title.
+    fn next_level(&mut self, ctx: &mut Context) {
+        self.load_campaign_level(ctx).unwrap();
+        if self.campaign_cleared {
+            self.campaign_cleared = false;
+            self.screen = Screen::Title;
+        } else {
+            self.start(ctx);
+        }
+    }
+    /// Starts a fresh run of the current level.
+    fn start(&mut self, ctx: &mut Context) {
+        // The snakes already fit on this level when it was loaded.
+        self.game = new_game(&self.config, &self.level).expect("no room for the snakes");
+        println!("Seed: {}", self.game.seed());
+        self.recording = Replay::new(&self.level, &self.game, self.config.players);
+        for input in &mut self.inputs {
+            input.clear();
+        }
+        self.play_time = Duration::from_secs(0);
+        self.timestep.reset();
+        self.prev_bodies = self
+            .game
+            .players
+            .iter()
+            .map(|p| p.snake.body.clone())
+            .collect();
+        self.delay = self.base_delay();
+        self.background_music = background_music(ctx);
+        self.background_music.play().unwrap();
+        self.screen = Screen::Playing;
+    }
+    /// Milliseconds per tick when no key is held.
+    fn base_delay(&self) -> u64 {
+        self.game.tick_interval()
+    }
+    fn pause(&mut self) {
+        if self.screen == Screen::Playing {
+            self.background_music.pause();
+            self.screen = Screen::Paused;
+        }
+    }
+    fn resume(&mut self) {
+        if self.screen == Screen::Paused {
+            self.background_music.resume();
+            self.screen = Screen::Playing;
+        }
+    }
+    fn game_over(&mut self) {
+        self.background_music.stop();
+        // Campaign runs are scored per level and versus rounds against the
+        // other player, so only endless solo games played by hand go in the
+        // high-score table.
+        let score = self.game.players[0].score.val;
+        self.screen = if self.campaign.is_none()
+            && self.versus.is_none()
+            && self.autopilot.is_none()
+            && self.high_scores.qualifies(&self.board(), score)
+        {
+            Screen::NameEntry
+        } else {
+            Screen::GameOver
+        };
+    }
+    fn round_over(&mut self) {
+        self.background_music.stop();
+        if let Some(ref mut versus) = self.versus {
+            self.round_winner = versus.record(&self.game);
+        }
+        self.screen = Screen::RoundOver;
+    }
+    /// Starts the next round, or a new match once this one is decided.
+    fn next_round(&mut self, ctx: &mut Context) {
+        if self.versus.as_ref().is_some_and(|v| v.winner().is_some()) {
+            self.versus = versus_match(&self.config);
+        }
+        self.start(ctx);
+    }
+    fn level_complete(&mut self, ctx: &mut Context) {
+        self.background_music.stop();
+        self.screen = Screen::LevelComplete;
+        if let Some(ref mut campaign) = self.campaign {
+            self.campaign_cleared = !campaign.advance();
+            if let Err(e) = save_progress(ctx, campaign.unlocked()) {
+                eprintln!("snake: could not save campaign progress: {}", e);
+            }
+        }
+    }
+    fn record_high_score(&mut self, ctx: &mut Context) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let name = highscores::clean_name(&self.player_name);
+        self.high_scores.insert(HighScore {
+            name: if name.is_empty() {
+                "anonymous".to_string()
+            } else {
+                name
+            },
+            score: self.game.players[0].score.val,
+            length: self.game.players[0].snake.body.len(),
+            duration_secs: self.play_time.as_secs(),
+            timestamp,
+            board: self.board(),
+            seed: self.game.seed(),
+        });
+        if let Err(e) = save_high_scores(ctx, &self.high_scores) {
+            eprintln!("snake: could not save high scores: {}", e);
+        }
+        self.screen = Screen::GameOver;
+    }
+    fn tick(&mut self, ctx: &mut Context) {
+        self.prev_bodies = self
+            .game
+            .players
+            .iter()
+            .map(|p| p.snake.body.clone())
+            .collect();
+        let humans = self.inputs.len();
+        let mut inputs = Vec::with_capacity(self.game.players.len());
+        for i in 0..self.game.players.len() {
+            if !self.game.players[i].alive {
+                inputs.push(None);
+                continue;
+            }
+            let controller: &mut dyn Controller = match self.autopilot {
+                Some(ref mut bot) if i == 0 => bot.as_mut(),
+                _ if i < humans => &mut self.inputs[i],
+                _ => self.bots[i - humans].as_mut(),
+            };
+            inputs.push(controller.next_move(&Snapshot::new(&self.game, i)));
+            if let Some(reason) = controller.failure() {
+                eprintln!("snake: {} disqualified: {}", snake_name(i, humans), reason);
+                self.game.disqualify(i);
+                self.recording.record_disqualified(i);
+            }
+        }
+        self.recording.record(&inputs);
+        for event in self.game.step(&inputs) {
+            match event {
+                Event::Moved | Event::Won => {}
+                Event::AteApple(_) => self.eating_sound.play().unwrap(),
+                Event::Died(_) => self.game_over_sound.play().unwrap(),
+            }
+        }
+        // With computer opponents the round is over for the people once
+        // they are all out, even if the bots could play on.
+        let humans_out = self.game.players[..humans].iter().all(|p| !p.alive);
+        if self.game.is_over() || humans_out {
+            match save_replay(ctx, &self.recording) {
+                Ok(path) => println!("Replay saved to {}", path.display()),
+                Err(e) => eprintln!("snake: could not save the replay: {}", e),
+            }
+        }
+        match self.game.outcome() {
+            _ if self.versus.is_some() && (self.game.is_over() || humans_out) => self.round_over(),
+            None => {}
+            Some(Outcome::GoalReached) | Some(Outcome::BoardFull) if self.campaign.is_some() => {
+                self.level_complete(ctx)
+            }
+            Some(_) => self.game_over(),
+        }
+    }
+    /// Plays the replay's next tick, if it has one left.
+    fn replay_step(&mut self) {
+        let prev_bodies = self
+            .game
+            .players
+            .iter()
+            .map(|p| p.snake.body.clone())
+            .collect();
+        let events = match self.playback {
+            Some(ref playback) => match playback.replay.play_tick(&mut self.game, playback.tick) {
+                Some(events) => events,
+                None => return,
+            },
+            None => return,
+        };
+        self.prev_bodies = prev_bodies;
+        for event in events {
+            match event {
+                Event::AteApple(_) => self.eating_sound.play().unwrap(),
+                Event::Died(_) => self.game_over_sound.play().unwrap(),
+                Event::Moved | Event::Won => {}
+            }
+        }
+        if let Some(ref mut playback) = self.playback {
+            playback.tick += 1;
+        }
+    }
+    /// Starts the replay again from its first tick.
+    fn restart_replay(&mut self) {
+        if let Some(ref mut playback) = self.playback {
+            playback.tick = 0;
+            self.game = playback.replay.game();
+        }
+        self.prev_bodies = self
+            .game
+            .players
+            .iter()
+            .map(|p| p.snake.body.clone())
+            .collect();
+        self.timestep.reset();
+    }
+    fn replay_key(&mut self, ctx: &mut Context, keycode: Keycode) {
+        let playback = match self.playback {
+            Some(ref mut playback) => playback,
+            None => return,
+        };
+        match keycode {
+            Keycode::Space => playback.paused = !playback.paused,
+            Keycode::Up | Keycode::Equals | Keycode::KpPlus => {
+                playback.speed = (playback.speed + 1).min(REPLAY_SPEEDS.len() - 1)
+            }
+            Keycode::Down | Keycode::Minus | Keycode::KpMinus => {
+                playback.speed = playback.speed.saturating_sub(1)
+            }
+            Keycode::Right | Keycode::Period => {
+                playback.paused = true;
+                self.replay_step();
+                self.timestep.reset();
+            }
+            Keycode::R | Keycode::Home => self.restart_replay(),
+            Keycode::Escape => ctx.quit().expect("Should never fail"),
+            _ => {}
+        }
+    }
+    fn replay_status(&self) -> String {
+        let playback = match self.playback {
+            Some(ref playback) => playback,
+            None => return String::new(),
+        };
+        let state = if playback.is_finished() {
+            "finished".to_string()
+        } else if playback.paused {
+            "paused".to_string()
+        } else {
+            format!("{}x", REPLAY_SPEEDS[playback.speed])
+        };
+        format!(
+            "Replay: tick {}/{}, {}   Space pause, Right step, Up/Down speed, R restart, Esc quit",
+            playback.tick,
+            playback.replay.len(),
+            state
+        )
+    }
+    /// Handles a bound key or button press from snake `player`.
+    fn act(&mut self, player: usize, action: Action, repeat: bool) {
+        match (self.screen, action) {
+            (_, Action::Pause) if repeat => {}
+            (Screen::Playing, Action::Pause) => self.pause(),
+            (Screen::Paused, Action::Pause) => self.resume(),
+            (Screen::Playing, _) => {
+                if let Some(action) = self.config.controls.interpret(action) {
+                    self.steer(player, action, repeat);
+                }
+            }
+            _ => {}
+        }
+    }
+    fn steer(&mut self, player: usize, action: Action, repeat: bool) {
+        let moving = self.game.players[player].snake.curr_dir;
+        let heading = self.inputs[player].last(moving);
+        let key = match action {
+            Action::Steer(dir) => Some(dir),
+            Action::TurnLeft => Some(heading.turn_left()),
+            Action::TurnRight => Some(heading.turn_right()),
+            Action::Pause => None,
+        };
+
+        if let Some(dir) = key {
+            let opposite = !moving;
+            if dir != opposite {
+                // Survival time is counted in ticks, so holding a key
+                // must not hurry them along on a "survive" level, and a
+                // bot's run must not be sped up from the keyboard.
+                let survival = matches!(self.game.goal(), Some(Goal::Survive(_)));
+                self.delay = if repeat && !survival && self.autopilot.is_none() {
+                    FAST_SPEED.min(self.base_delay())
+                } else {
+                    self.base_delay()
+                };
+                self.since_key = Duration::from_secs(0);
+            }
+            if !repeat {
+                self.inputs[player].push(dir);
+            }
+        }
+    }
+    /// Which snake a key belongs to and what it does. With two people
+    /// playing, player two's bindings are checked first; computer snakes
+    /// never take keys.
+    fn binding(&self, key: &str) -> Option<(usize, Action)> {
+        let second = if self.config.players > 1 {
+            self.config.bindings2.action(key).map(|a| (1, a))
+        } else {
+            None
+        };
+        second
+            .or_else(|| self.config.bindings.action(key).map(|a| (0, a)))
+            .filter(|&(player, _)| player < self.inputs.len())
+    }
+    fn round_over_lines(&self) -> (String, Vec<String>) {
+        let versus = match self.versus {
+            Some(ref versus) => versus,
+            None => return (String::new(), vec![]),
+        };
+        let humans = self.config.players;
+        let heading = match (versus.winner(), self.round_winner) {
+            (Some(player), _) => format!("{} wins the match!", snake_name(player, humans)),
+            (None, Some(player)) => format!("{} wins the round!", snake_name(player, humans)),
+            (None, None) => "Round drawn".to_string(),
+        };
+        let wins: Vec<String> = versus.wins().iter().map(u32::to_string).collect();
+        let scores: Vec<String> = self
+            .game
+            .players
+            .iter()
+            .enumerate()
+            .map(|(i, p)| format!("{} scored {}", short_name(i, humans), p.score.val))
+            .collect();
+        let next = if versus.winner().is_some() {
+            "Enter for a new match, Esc to quit"
+        } else {
+            "Enter for the next round, Esc to quit"
+        };
+        let lines = vec![
+            format!(
+                "Round {}: {} (first to {})",
+                versus.rounds(),
+                wins.join(" - "),
+                versus.rounds_to_win()
+            ),
+            scores.join("   "),
+            next.to_string(),
+        ];
+        (heading, lines)
+    }
+    fn game_over_lines(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Score: {}   Length: {}   Time: {}",
+                self.game.players[0].score.val,
+                self.game.players[0].snake.body.len(),
+                format_duration(self.play_time)
+            ),
+            "Enter to play again, Esc to quit".to_string(),
+        ]
+    }
+    /// The board high scores are kept for: this one, with the current
+    /// layout or level and difficulty.
+    fn board(&self) -> Board {
+        Board {
+            cols: self.game.bounds.cols,
+            rows: self.game.bounds.rows,
+            wrap: self.game.bounds.wrap,
+            level: match self.config.level {
+                Some(ref path) => path.clone(),
+                None => self.config.layout.to_string(),
+            },
+            difficulty: self.game.difficulty(),
+        }
+    }
+    fn high_score_lines(&self) -> Vec<String> {
+        let board = self.board();
+        let entries = self.high_scores.entries(&board);
+        if entries.is_empty() {
+            return vec![];
+        }
+        let heading = format!(
+            "High scores: {}x{} {}{}, {}",
+            board.cols,
+            board.rows,
+            board.level,
+            if board.wrap { " wrapped" } else { "" },
+            board.difficulty
+        );
+        let mut lines = vec![String::new(), heading];
+        for (i, e) in entries.iter().enumerate() {
+            lines.push(format!(
+                "{}. {}  {}  (length {}, {}, {})",
+                i + 1,
+                e.name,
+                e.score,
+                e.length,
+                format_duration(Duration::from_secs(e.duration_secs)),
+                e.date()
+            ));
+        }
+        lines
+    }
 }
 
 impl event::EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        if !self.game_over {
-            if self.last_key_moment.elapsed() >= Duration::from_millis(self.delay) {
-                self.delay = SLOW_SPEED;
-            }
-            if self.last_move.elapsed() >= Duration::from_millis(self.delay) {
-                self.last_move = Instant::now();
-                self.snake.advance();
-                if !self.snake.bounds_check(&self.bounds) || !self.snake.body_check() {
-                    self.game_over = true;
-                    self.background_music.stop();
-                    self.game_over_sound.play().unwrap();
-                    while self.game_over_sound.playing() {
-                        ggez::timer::yield_now();
-                    }
-                    ctx.quit()?;
-                }
-                if self.apple.dist_to(&self.snake.head()) < CELL_DIAMETER as f32 {
-                    self.eating_sound.play().unwrap();
-                    self.apple.eaten();
-                    self.score.increment();
-                } else {
-                    self.snake.shorten_tail();
+        let now = Instant::now();
+        let frame_time = now - self.last_frame;
+        self.last_frame = now;
+        // The simulation clock only runs while playing, so a pause picks
+        // up exactly where it left off.
+        if self.screen == Screen::Playing {
+            self.play_time += frame_time;
+            self.since_key += frame_time;
+            if self.since_key >= Duration::from_millis(self.delay) {
+                self.delay = self.base_delay();
+            }
+            self.timestep.set_step(Duration::from_millis(self.delay));
+            self.timestep.advance(frame_time);
+            while self.screen == Screen::Playing && self.timestep.consume() {
+                self.tick(ctx);
+            }
+        } else if self.screen == Screen::Replay {
+            let speed = match self.playback {
+                Some(ref playback) if !playback.paused && !playback.is_finished() => {
+                    REPLAY_SPEEDS[playback.speed]
                 }
+                _ => return Ok(()),
+            };
+            let step = (self.base_delay() as f64 / speed).max(1.0);
+            self.timestep.set_step(Duration::from_millis(step as u64));
+            self.timestep.advance(frame_time);
+            while self.timestep.consume() {
+                self.replay_step();
             }
         }
         Ok(())
@@ -311,44 +1071,296 @@ impl event::EventHandler for MainState {
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx);
-        self.snake.draw(ctx)?;
-        self.apple.draw(ctx)?;
-        self.score.draw(ctx)?;
+        match self.screen {
+            Screen::Title => {
+                // Player two's keys win when both players bind them.
+                let two_players = self.config.players > 1;
+                let taken = Some(&self.config.bindings2).filter(|_| two_players);
+                let player1 = controls_line(&self.config.bindings, taken, self.config.controls);
+                let mut lines = vec![
+                    if two_players {
+                        format!("Player 1: {}", player1)
+                    } else {
+                        player1
+                    },
+                    "Enter to start, Esc to quit".to_string(),
+                    format!("Difficulty: {}", self.config.difficulty),
+                    format!("Controls: {} (Tab to change)", self.config.controls),
+                ];
+                if let Some(ref bot) = self.config.bot {
+                    lines.push(format!("Player 1 is steered by the {} bot", bot));
+                }
+                match (&self.campaign, &self.versus) {
+                    (Some(campaign), _) => lines.extend(campaign_lines(campaign)),
+                    (None, Some(versus)) => {
+                        lines.push(String::new());
+                        if self.config.players > 1 {
+                            lines.push(format!(
+                                "Player 2: {}",
+                                controls_line(&self.config.bindings2, None, self.config.controls)
+                            ));
+                        }
+                        if self.config.opponents > 0 {
+                            lines.push(format!(
+                                "Computer opponents: {} ({})",
+                                self.config.opponents, self.config.ai
+                            ));
+                        }
+                        lines.push(format!(
+                            "{} snakes, first to {} rounds wins",
+                            versus.wins().len(),
+                            versus.rounds_to_win()
+                        ));
+                    }
+                    (None, None) => lines.extend(self.high_score_lines()),
+                }
+                self.score_board.draw_centered(ctx, "Snake", &lines)?;
+            }
+            Screen::Playing | Screen::Paused | Screen::Replay => {
+                draw_walls(ctx, &self.layout, &self.game.walls)?;
+                // A stopped replay shows the board as it is, not part way
+                // through a move.
+                let alpha = match self.playback {
+                    Some(ref playback) if playback.paused || playback.is_finished() => 1.0,
+                    _ => self.timestep.alpha(),
+                };
+                for (i, p) in self.game.players.iter().enumerate() {
+                    if !p.alive {
+                        continue;
+                    }
+                    draw_snake(
+                        ctx,
+                        &self.layout,
+                        &self.game.bounds,
+                        &p.snake,
+                        &self.prev_bodies[i],
+                        alpha,
+                        SNAKE_COLORS[i % SNAKE_COLORS.len()],
+                    )?;
+                }
+                self.game.apple.draw(ctx, &self.layout)?;
+                self.score_board
+                    .draw(ctx, &self.game, self.config.players)?;
+                if self.screen == Screen::Paused {
+                    graphics::set_color(ctx, graphics::Color::new(0.0, 0.0, 0.0, 0.6))?;
+                    let (width, height) = graphics::get_size(ctx);
+                    let screen = graphics::Rect::new(0.0, 0.0, width as f32, height as f32);
+                    graphics::rectangle(ctx, DrawMode::Fill, screen)?;
+                    let lines = vec!["P or Esc to resume".to_string()];
+                    self.score_board.draw_centered(ctx, "Paused", &lines)?;
+                }
+                if self.screen == Screen::Replay {
+                    let status = self.replay_status();
+                    self.score_board.draw_status(ctx, &status)?;
+                }
+            }
+            Screen::NameEntry => {
+                let lines = vec![
+                    format!("Score: {}", self.game.players[0].score.val),
+                    format!("Name: {}_", self.player_name),
+                    "Type your name and press Enter".to_string(),
+                ];
+                self.score_board
+                    .draw_centered(ctx, "New high score!", &lines)?;
+            }
+            Screen::LevelComplete => {
+                let (heading, next) = match self.campaign {
+                    Some(ref campaign) if !self.campaign_cleared => (
+                        "Level complete!",
+                        format!("Next: {}", level_title(campaign)),
+                    ),
+                    _ => ("Campaign complete!", "Every level cleared".to_string()),
+                };
+                let lines = vec![
+                    format!(
+                        "Score: {}   Time: {}",
+                        self.game.players[0].score.val,
+                        format_duration(self.play_time)
+                    ),
+                    next,
+                    "Enter to continue, Esc to quit".to_string(),
+                ];
+                self.score_board.draw_centered(ctx, heading, &lines)?;
+            }
+            Screen::RoundOver => {
+                let (heading, lines) = self.round_over_lines();
+                self.score_board.draw_centered(ctx, &heading, &lines)?;
+            }
+            Screen::GameOver => {
+                let heading = match self.game.outcome() {
+                    Some(Outcome::BoardFull) => "Board full - you win!",
+                    Some(Outcome::GoalReached) => "Goal reached - you win!",
+                    _ => "Game over",
+                };
+                let mut lines = self.game_over_lines();
+                lines.extend(self.high_score_lines());
+                self.score_board.draw_centered(ctx, heading, &lines)?;
+            }
+        }
         graphics::present(ctx);
-        ggez::timer::yield_now();
         Ok(())
     }
-    fn key_down_event(&mut self, _ctx: &mut Context, keycode: Keycode, _keymod: Mod, repeat: bool) {
-        let key = match keycode {
-            Keycode::Up => Some(Direction::Up),
-            Keycode::Left => Some(Direction::Left),
-            Keycode::Down => Some(Direction::Down),
-            Keycode::Right => Some(Direction::Right),
-            _ => None,
+    fn key_down_event(&mut self, ctx: &mut Context, keycode: Keycode, _keymod: Mod, repeat: bool) {
+        match (self.screen, keycode) {
+            (Screen::Title, Keycode::Return) | (Screen::GameOver, Keycode::Return) => {
+                self.start(ctx)
+            }
+            (Screen::LevelComplete, Keycode::Return) => self.next_level(ctx),
+            (Screen::RoundOver, Keycode::Return) => self.next_round(ctx),
+            (Screen::Title, Keycode::Left) => self.select_level(ctx, -1),
+            (Screen::Title, Keycode::Right) => self.select_level(ctx, 1),
+            (Screen::Title, Keycode::Tab) => self.config.controls = self.config.controls.toggle(),
+            (Screen::Title, Keycode::Escape)
+            | (Screen::GameOver, Keycode::Escape)
+            | (Screen::LevelComplete, Keycode::Escape)
+            | (Screen::RoundOver, Keycode::Escape) => ctx.quit().expect("Should never fail"),
+            (Screen::Playing, Keycode::Escape) => self.pause(),
+            (Screen::Paused, Keycode::Escape) => self.resume(),
+            (Screen::Playing, _) | (Screen::Paused, _) => {
+                if let Some((player, action)) = self.binding(&keycode.name()) {
+                    self.act(player, action, repeat);
+                }
+            }
+            (Screen::Replay, _) => self.replay_key(ctx, keycode),
+            (Screen::NameEntry, Keycode::Return) => self.record_high_score(ctx),
+            (Screen::NameEntry, Keycode::Backspace) => {
+                self.player_name.pop();
+            }
+            _ => {}
+        }
+    }
+    fn controller_button_down_event(&mut self, ctx: &mut Context, btn: Button, _instance_id: i32) {
+        let name = format!("pad:{}", btn.string());
+        match (self.screen, btn) {
+            (Screen::Title, Button::Start)
+            | (Screen::Title, Button::A)
+            | (Screen::GameOver, Button::Start)
+            | (Screen::GameOver, Button::A) => self.start(ctx),
+            (Screen::LevelComplete, Button::Start) | (Screen::LevelComplete, Button::A) => {
+                self.next_level(ctx)
+            }
+            (Screen::RoundOver, Button::Start) | (Screen::RoundOver, Button::A) => {
+                self.next_round(ctx)
+            }
+            _ => {
+                if let Some((player, action)) = self.binding(&name) {
+                    self.act(player, action, false);
+                }
+            }
+        }
+    }
+    fn controller_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        axis: Axis,
+        value: i16,
+        _instance_id: i32,
+    ) {
+        match axis {
+            Axis::LeftX => self.stick.0 = value,
+            Axis::LeftY => self.stick.1 = value,
+            _ => return,
+        }
+        // Steer once each time the stick leaves the middle or swings to a
+        // new direction, rather than on every tiny movement.
+        let (x, y) = (i32::from(self.stick.0), i32::from(self.stick.1));
+        let dir = if x.abs().max(y.abs()) < i32::from(STICK_DEADZONE) {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x < 0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            })
+        } else {
+            Some(if y < 0 {
+                Direction::Up
+            } else {
+                Direction::Down
+            })
         };
-
-        if let Some(dir) = key {
-            let opposite = !self.snake.curr_dir;
-            if dir != opposite {
-                self.delay = if repeat { FAST_SPEED } else { SLOW_SPEED };
-                self.last_key_moment = Instant::now();
-                self.snake.curr_dir = dir;
+        if dir != self.stick_dir {
+            self.stick_dir = dir;
+            if let Some(dir) = dir {
+                self.act(0, Action::Steer(dir), false);
             }
         }
     }
-}
-
-fn resource_path() -> PathBuf {
-    match env::var("CARGO_MANIFEST_DIR") {
-        Ok(manifest_dir) => Path::new(&manifest_dir).join("resources"),
-        Err(_) => PathBuf::from("resources"),
+    fn text_input_event(&mut self, _ctx: &mut Context, text: String) {
+        if self.screen == Screen::NameEntry {
+            self.player_name.push_str(&text);
+            self.player_name = self
+                .player_name
+                .chars()
+                .take(highscores::MAX_NAME_LEN)
+                .collect();
+        }
+    }
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        if !gained {
+            self.pause();
+        }
     }
 }
 
 pub fn main() {
-    let c = conf::Conf::new();
+    let mut config = match Config::load(env::args().skip(1)) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("snake: {}", e);
+            process::exit(2);
+        }
+    };
+    let replay = match config.replay {
+        Some(ref path) => match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Replay::parse(&text).map_err(|e| e.to_string()))
+        {
+            Ok(replay) => Some(replay),
+            Err(e) => {
+                eprintln!("snake: {}: {}", path, e);
+                process::exit(2);
+            }
+        },
+        None => None,
+    };
+    // A replay brings its own board and snakes.
+    if let Some(ref replay) = replay {
+        config.cols = replay.level.bounds.cols;
+        config.rows = replay.level.bounds.rows;
+        config.wrap = replay.level.bounds.wrap;
+        config.difficulty = replay.difficulty;
+        config.players = replay.players;
+        config.opponents = replay.snakes - replay.players;
+        config.campaign = false;
+    }
+    let bounds = Bounds::new(config.cols, config.rows, config.wrap);
+    let layout = Layout::new(&bounds, config.cell_size);
+    let mut c = conf::Conf::new();
+    c.window_mode.width = layout.width as u32;
+    c.window_mode.height = layout.height as u32;
     let ctx = &mut Context::load_from_conf("snake", "ggez", c).unwrap();
     ctx.filesystem.mount(&resource_path(), true);
-    let state = &mut MainState::new(ctx).unwrap();
+    let loaded = if let Some(ref replay) = replay {
+        Ok((replay.level.clone(), None))
+    } else if config.campaign {
+        load_campaign(ctx).map(|campaign| (campaign.level().clone(), Some(campaign)))
+    } else {
+        load_level(ctx, &config).map(|level| (level, None))
+    };
+    let (level, campaign) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("snake: {}", e);
+            process::exit(2);
+        }
+    };
+    let state = &mut match MainState::new(ctx, config, level, campaign, replay) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("snake: {}", e);
+            process::exit(2);
+        }
+    };
     event::run(ctx, state).unwrap();
 }
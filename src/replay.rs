@@ -0,0 +1,344 @@
+use crate::difficulty::Difficulty;
+use crate::game::{Direction, Event, Game};
+use crate::level::Level;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write;
+
+const MAGIC: &str = "snake-replay 1";
+
+/// Everything needed to play a game again tick for tick: the board, the
+/// seed and settings it started from, and what every snake was told to do
+/// on each tick.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub level: Level,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    /// How many of the snakes were steered by people.
+    pub players: usize,
+    pub snakes: usize,
+    moves: Vec<Vec<Option<Direction>>>,
+    /// Snakes disqualified just before a tick, as (tick, snake).
+    disqualified: Vec<(usize, usize)>,
+}
+
+impl Replay {
+    /// Starts recording `game`, which must be fresh from `level`.
+    pub fn new(level: &Level, game: &Game, players: usize) -> Replay {
+        Replay {
+            level: level.clone(),
+            seed: game.seed(),
+            difficulty: game.difficulty(),
+            players,
+            snakes: game.players.len(),
+            moves: Vec::new(),
+            disqualified: Vec::new(),
+        }
+    }
+    /// Adds one tick's inputs, as passed to `Game::step`.
+    pub fn record(&mut self, inputs: &[Option<Direction>]) {
+        let mut tick = inputs.to_vec();
+        tick.resize(self.snakes, None);
+        self.moves.push(tick);
+    }
+    /// Notes that `snake` was disqualified before the tick about to be
+    /// recorded.
+    pub fn record_disqualified(&mut self, snake: usize) {
+        self.disqualified.push((self.moves.len(), snake));
+    }
+    /// Plays tick `tick` of the replay on `game`, which must have played
+    /// every tick before it. Returns `None` past the end.
+    pub fn play_tick(&self, game: &mut Game, tick: usize) -> Option<Vec<Event>> {
+        let inputs = self.inputs(tick)?;
+        for &(at, snake) in &self.disqualified {
+            if at == tick {
+                game.disqualify(snake);
+            }
+        }
+        Some(game.step(inputs))
+    }
+    /// Ticks recorded.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+    /// The inputs for tick `tick`, counting from 0.
+    pub fn inputs(&self, tick: usize) -> Option<&[Option<Direction>]> {
+        self.moves.get(tick).map(Vec::as_slice)
+    }
+    /// The game as it was before the first tick.
+    pub fn game(&self) -> Game {
+        Game::with_snakes(&self.level, self.snakes, self.difficulty, self.seed)
+            .expect("`parse` checks that the snakes fit")
+    }
+
+    /// Reads a replay from its text form.
+    ///
+    /// The first line is `snake-replay 1`, followed by `key = value` lines
+    /// for `seed`, `difficulty`, `players`, `snakes` and `wrap`. A `moves`
+    /// line starts the inputs, one line per tick with a character per snake
+    /// (`U`, `D`, `L`, `R`, or `.` for no input), and `*<n>` after a line
+    /// repeats it `n` times. `!<snake>` takes that snake, counting from 0,
+    /// off the board before the next tick. A `level` line ends them, and
+    /// the rest of the file is the level in its own text form.
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(n, line)| (n + 1, line));
+        match lines.next() {
+            Some((_, line)) if line.trim() == MAGIC => {}
+            _ => return Err(ReplayError::new(1, format!("expected `{}`", MAGIC))),
+        }
+
+        let (mut seed, mut difficulty, mut players, mut snakes, mut wrap) =
+            (None, None, None, None, false);
+        let mut moves_start = None;
+        for (n, line) in &mut lines {
+            let line = line.trim();
+            if line == "moves" {
+                moves_start = Some(n);
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(ReplayError::new(n, "expected `key = value` or `moves`")),
+            };
+            let invalid = || ReplayError::new(n, format!("invalid value {:?} for {}", value, key));
+            match key {
+                "seed" => seed = Some(value.parse().map_err(|_| invalid())?),
+                "difficulty" => difficulty = Some(value.parse().map_err(|_| invalid())?),
+                "players" => players = Some(value.parse().map_err(|_| invalid())?),
+                "snakes" => snakes = Some(value.parse().map_err(|_| invalid())?),
+                "wrap" => wrap = value.parse().map_err(|_| invalid())?,
+                _ => return Err(ReplayError::new(n, format!("unknown header {:?}", key))),
+            }
+        }
+        let moves_start =
+            moves_start.ok_or_else(|| ReplayError::new(text.lines().count(), "missing `moves`"))?;
+        let missing = |what: &str| ReplayError::new(moves_start, format!("missing {}", what));
+        let seed = seed.ok_or_else(|| missing("seed"))?;
+        let difficulty = difficulty.ok_or_else(|| missing("difficulty"))?;
+        let snakes: usize = snakes.ok_or_else(|| missing("snakes"))?;
+        let players = players.unwrap_or(1);
+        if snakes == 0 || players > snakes {
+            return Err(ReplayError::new(
+                moves_start,
+                "there must be a snake for every player",
+            ));
+        }
+
+        let mut moves = Vec::new();
+        let mut disqualified = Vec::new();
+        let mut level_start = None;
+        for (n, line) in &mut lines {
+            let line = line.trim();
+            if line == "level" {
+                level_start = Some(n);
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(snake) = line.strip_prefix('!') {
+                match snake.parse::<usize>() {
+                    Ok(snake) if snake < snakes => disqualified.push((moves.len(), snake)),
+                    _ => return Err(ReplayError::new(n, format!("no snake {:?}", snake))),
+                }
+                continue;
+            }
+            let (tick, count) = match line.split_once('*') {
+                Some((tick, count)) => match count.parse::<usize>() {
+                    Ok(count) => (tick, count),
+                    Err(_) => return Err(ReplayError::new(n, format!("bad count {:?}", count))),
+                },
+                None => (line, 1),
+            };
+            if tick.chars().count() != snakes {
+                return Err(ReplayError::new(
+                    n,
+                    format!("expected a move for each of {} snakes", snakes),
+                ));
+            }
+            let mut inputs = Vec::with_capacity(snakes);
+            for c in tick.chars() {
+                inputs.push(match c {
+                    'U' => Some(Direction::Up),
+                    'D' => Some(Direction::Down),
+                    'L' => Some(Direction::Left),
+                    'R' => Some(Direction::Right),
+                    '.' => None,
+                    _ => return Err(ReplayError::new(n, format!("unexpected {:?}", c))),
+                });
+            }
+            for _ in 0..count {
+                moves.push(inputs.clone());
+            }
+        }
+        let level_start =
+            level_start.ok_or_else(|| ReplayError::new(text.lines().count(), "missing `level`"))?;
+
+        let level_text: Vec<&str> = lines.map(|(_, line)| line).collect();
+        let mut level = Level::parse(&level_text.join("\n"))
+            .map_err(|e| ReplayError::new(level_start + e.line, e.message))?;
+        level.bounds.wrap = wrap;
+        Game::with_snakes(&level, snakes, difficulty, seed)
+            .map_err(|e| ReplayError::new(level_start, e))?;
+        Ok(Replay {
+            level,
+            seed,
+            difficulty,
+            players,
+            snakes,
+            moves,
+            disqualified,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        writeln!(text, "{}", MAGIC).unwrap();
+        writeln!(text, "seed = {}", self.seed).unwrap();
+        writeln!(
+            text,
+            "difficulty = {}",
+            self.difficulty.to_string().to_lowercase()
+        )
+        .unwrap();
+        writeln!(text, "players = {}", self.players).unwrap();
+        writeln!(text, "snakes = {}", self.snakes).unwrap();
+        writeln!(text, "wrap = {}", self.level.bounds.wrap).unwrap();
+        text.push_str("moves\n");
+        // Most ticks have no input at all, so runs of the same line are
+        // written once with a count.
+        let mut i = 0;
+        while i < self.moves.len() {
+            for &(_, snake) in self.disqualified.iter().filter(|&&(at, _)| at == i) {
+                writeln!(text, "!{}", snake).unwrap();
+            }
+            // A run stops short of the next disqualification.
+            let next_out = self
+                .disqualified
+                .iter()
+                .map(|&(at, _)| at)
+                .filter(|&at| at > i)
+                .min()
+                .unwrap_or(self.moves.len());
+            let run = self.moves[i..next_out]
+                .iter()
+                .take_while(|&tick| *tick == self.moves[i])
+                .count();
+            let line: String = self.moves[i]
+                .iter()
+                .map(|input| match input {
+                    Some(Direction::Up) => 'U',
+                    Some(Direction::Down) => 'D',
+                    Some(Direction::Left) => 'L',
+                    Some(Direction::Right) => 'R',
+                    None => '.',
+                })
+                .collect();
+            if run > 1 {
+                writeln!(text, "{}*{}", line, run).unwrap();
+            } else {
+                writeln!(text, "{}", line).unwrap();
+            }
+            i += run;
+        }
+        text.push_str("level\n");
+        text.push_str(&self.level.to_text());
+        text
+    }
+}
+
+/// A problem in a replay file, with the 1-based line it was found at.
+#[derive(Debug)]
+pub struct ReplayError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ReplayError {
+    fn new<S: Into<String>>(line: usize, message: S) -> ReplayError {
+        ReplayError {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ReplayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Bounds, Death};
+
+    /// A three-snake game where snake 2 is disqualified before tick 4,
+    /// with a few turns and long runs of no input.
+    fn recorded() -> (Replay, Game) {
+        let level = Level::empty(Bounds::new(20, 15, false));
+        let mut game = Game::with_snakes(&level, 3, Difficulty::Hard, 42).unwrap();
+        let mut replay = Replay::new(&level, &game, 1);
+        let turns = [
+            (2, vec![Some(Direction::Up), None, None]),
+            (6, vec![None, Some(Direction::Down), None]),
+        ];
+        for tick in 0..10 {
+            if tick == 4 {
+                game.disqualify(2);
+                replay.record_disqualified(2);
+            }
+            let inputs = turns
+                .iter()
+                .find(|(at, _)| *at == tick)
+                .map_or(vec![None; 3], |(_, inputs)| inputs.clone());
+            replay.record(&inputs);
+            game.step(&inputs);
+        }
+        (replay, game)
+    }
+
+    #[test]
+    fn text_round_trip_keeps_disqualifications() {
+        let (replay, played) = recorded();
+        let text = replay.to_text();
+        assert!(text.contains("\n!2\n"));
+        let parsed = Replay::parse(&text).unwrap();
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.len(), 10);
+        assert_eq!((parsed.seed, parsed.players, parsed.snakes), (42, 1, 3));
+
+        let mut game = parsed.game();
+        for tick in 0..parsed.len() {
+            parsed.play_tick(&mut game, tick).unwrap();
+        }
+        assert!(parsed.play_tick(&mut game, parsed.len()).is_none());
+        for (a, b) in game.players.iter().zip(&played.players) {
+            assert_eq!(a.snake.body, b.snake.body);
+            assert_eq!(a.death, b.death);
+        }
+        assert_eq!(game.players[2].death, Some(Death::Disqualified));
+        assert_eq!(game.apple.pos, played.apple.pos);
+    }
+
+    #[test]
+    fn parse_errors_give_the_line() {
+        let (replay, _) = recorded();
+        let text = replay.to_text();
+        let line = |text: &str| Replay::parse(text).unwrap_err().line;
+        assert_eq!(line("snake-replay 2\n"), 1);
+        assert_eq!(line(&text.replace("seed = 42", "seed = x")), 2);
+        assert_eq!(line(&text.replace("!2", "!3")), 11);
+        assert_eq!(line(&text.replacen("...", "..", 1)), 8);
+    }
+}
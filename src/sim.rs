@@ -0,0 +1,130 @@
+//! Games played start to finish by controllers alone, with no window or
+//! clock, for the batch tools.
+
+use crate::controller::{Controller, Snapshot};
+use crate::difficulty::Difficulty;
+use crate::game::Game;
+use crate::level::Level;
+use crate::replay::Replay;
+
+/// Ticks allowed per open cell before a game is cut off by default. A bot
+/// that never eats can circle forever, and this leaves a steady eater time
+/// to cover a good part of the board whatever its size.
+pub const TICKS_PER_CELL: u64 = 100;
+
+/// The default tick limit for games on `level`.
+pub fn default_max_ticks(level: &Level) -> u64 {
+    let cells = (level.bounds.cols * level.bounds.rows) as usize - level.walls.len();
+    cells as u64 * TICKS_PER_CELL
+}
+
+/// A game played out by `play`.
+pub struct Played {
+    pub game: Game,
+    /// The recording, if one was asked for.
+    pub replay: Option<Replay>,
+    /// The tick each snake died on, if it did.
+    pub died_at: Vec<Option<u64>>,
+}
+
+impl Played {
+    /// Ticks snake `player` stayed on the board.
+    pub fn lasted(&self, player: usize) -> u64 {
+        self.died_at[player].unwrap_or_else(|| self.game.ticks())
+    }
+}
+
+/// Plays one game with a snake for each controller until it is over or
+/// `max_ticks` have passed, recording it if `record` is set. Controllers
+/// that fail are disqualified. Fails only if the snakes do not fit on the
+/// level.
+pub fn play(
+    level: &Level,
+    difficulty: Difficulty,
+    seed: u64,
+    controllers: &mut [Box<dyn Controller>],
+    max_ticks: u64,
+    record: bool,
+) -> Result<Played, String> {
+    let mut game = Game::with_snakes(level, controllers.len(), difficulty, seed)?;
+    let mut replay = if record {
+        Some(Replay::new(level, &game, 0))
+    } else {
+        None
+    };
+    let mut died_at = vec![None; controllers.len()];
+    while !game.is_over() && game.ticks() < max_ticks {
+        let mut inputs = Vec::with_capacity(controllers.len());
+        for (i, controller) in controllers.iter_mut().enumerate() {
+            if !game.players[i].alive {
+                inputs.push(None);
+                continue;
+            }
+            inputs.push(controller.next_move(&Snapshot::new(&game, i)));
+            if controller.failure().is_some() {
+                game.disqualify(i);
+                if let Some(ref mut replay) = replay {
+                    replay.record_disqualified(i);
+                }
+            }
+        }
+        if let Some(ref mut replay) = replay {
+            replay.record(&inputs);
+        }
+        game.step(&inputs);
+        for (i, p) in game.players.iter().enumerate() {
+            if !p.alive && died_at[i].is_none() {
+                died_at[i] = Some(game.ticks());
+            }
+        }
+    }
+    Ok(Played {
+        game,
+        replay,
+        died_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AiKind;
+    use crate::game::Bounds;
+    use crate::level::BuiltinLevel;
+
+    #[test]
+    fn the_tick_limit_grows_with_the_open_cells() {
+        let open = Level::empty(Bounds::new(20, 10, false));
+        assert_eq!(default_max_ticks(&open), 200 * TICKS_PER_CELL);
+        let boxed = BuiltinLevel::Box.build(Bounds::new(20, 10, false));
+        let walls = boxed.walls.len() as u64;
+        assert_eq!(default_max_ticks(&boxed), (200 - walls) * TICKS_PER_CELL);
+        let big = Level::empty(Bounds::new(1000, 1000, false));
+        assert_eq!(default_max_ticks(&big), 1_000_000 * TICKS_PER_CELL);
+    }
+
+    #[test]
+    fn plays_to_the_limit_and_records_on_request() {
+        let level = Level::empty(Bounds::new(12, 12, false));
+        let bots = || vec![AiKind::Survivor.controller(), AiKind::Greedy.controller()];
+        let played = play(&level, Difficulty::Normal, 5, &mut bots(), 40, false).unwrap();
+        assert!(played.replay.is_none());
+        assert!(played.game.ticks() <= 40);
+        for i in 0..2 {
+            assert_eq!(played.died_at[i].is_none(), played.game.players[i].alive);
+            assert!(played.lasted(i) <= played.game.ticks());
+        }
+
+        let recorded = play(&level, Difficulty::Normal, 5, &mut bots(), 40, true).unwrap();
+        let replay = recorded.replay.unwrap();
+        assert_eq!(replay.len() as u64, recorded.game.ticks());
+        assert_eq!(recorded.died_at, played.died_at);
+    }
+
+    #[test]
+    fn snakes_that_do_not_fit_are_an_error() {
+        let level = Level::empty(Bounds::new(4, 4, false));
+        let mut bots: Vec<_> = (0..8).map(|_| AiKind::Greedy.controller()).collect();
+        assert!(play(&level, Difficulty::Normal, 0, &mut bots, 10, false).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+/// Longest stretch of wall-clock time the simulation will try to catch up
+/// on at once. Anything beyond this (a dragged window, a debugger pause) is
+/// dropped instead of being replayed as a burst of ticks.
+pub const MAX_LAG: Duration = Duration::from_millis(250);
+
+/// Fixed-timestep accumulator.
+///
+/// Elapsed frame time is added with `advance`, then `consume` is called in a
+/// loop, running one simulation tick each time it returns `true`. The
+/// leftover fraction of a tick is exposed through `alpha` for interpolated
+/// rendering.
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration) -> FixedTimestep {
+        FixedTimestep {
+            step,
+            accumulator: Duration::from_secs(0),
+        }
+    }
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+    /// Changes the tick length; time already accumulated is kept.
+    pub fn set_step(&mut self, step: Duration) {
+        self.step = step;
+    }
+    pub fn advance(&mut self, elapsed: Duration) {
+        self.accumulator = (self.accumulator + elapsed).min(self.step.max(MAX_LAG));
+    }
+    /// Takes one tick's worth of time off the accumulator if there is one.
+    pub fn consume(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            true
+        } else {
+            false
+        }
+    }
+    /// How far into the next tick we are, from 0.0 to 1.0.
+    pub fn alpha(&self) -> f32 {
+        let step = self.step.as_secs_f32();
+        if step > 0.0 {
+            (self.accumulator.as_secs_f32() / step).min(1.0)
+        } else {
+            0.0
+        }
+    }
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::from_secs(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    /// Ticks run by draining `timestep` after a frame of `frame` ms.
+    fn ticks_after(timestep: &mut FixedTimestep, frame: u64) -> usize {
+        timestep.advance(ms(frame));
+        let mut ticks = 0;
+        while timestep.consume() {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn runs_one_tick_per_step_of_elapsed_time() {
+        let mut timestep = FixedTimestep::new(ms(10));
+        assert_eq!(ticks_after(&mut timestep, 35), 3);
+        assert!((timestep.alpha() - 0.5).abs() < 1e-3);
+        assert_eq!(ticks_after(&mut timestep, 4), 0);
+        assert_eq!(ticks_after(&mut timestep, 1), 1);
+        let total: usize = (0..60).map(|_| ticks_after(&mut timestep, 16)).sum();
+        assert_eq!(total, 96);
+    }
+
+    #[test]
+    fn long_frames_are_clamped_to_max_lag() {
+        let mut timestep = FixedTimestep::new(ms(10));
+        let most = (MAX_LAG.as_millis() / 10) as usize;
+        assert_eq!(ticks_after(&mut timestep, 5_000), most);
+        // A step longer than the lag still gets its one tick.
+        let mut slow = FixedTimestep::new(ms(400));
+        assert_eq!(ticks_after(&mut slow, 5_000), 1);
+    }
+
+    #[test]
+    fn alpha_stays_below_one_after_draining() {
+        let mut timestep = FixedTimestep::new(ms(7));
+        for frame in (1..200).map(|i| i * 13 % 41) {
+            ticks_after(&mut timestep, frame);
+            let alpha = timestep.alpha();
+            assert!((0.0..1.0).contains(&alpha), "alpha {}", alpha);
+        }
+    }
+
+    #[test]
+    fn changing_the_step_keeps_the_time_and_reset_drops_it() {
+        let mut timestep = FixedTimestep::new(ms(10));
+        timestep.advance(ms(25));
+        timestep.set_step(ms(5));
+        assert_eq!(ticks_after(&mut timestep, 0), 5);
+        timestep.advance(ms(4));
+        timestep.reset();
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+}
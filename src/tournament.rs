@@ -0,0 +1,295 @@
+//! Bots playing each other over many games, rated by how they place.
+
+use crate::controller::Controller;
+use crate::difficulty::Difficulty;
+use crate::game::{self, Death, Game, Outcome};
+use crate::level::Level;
+use crate::replay::Replay;
+use crate::sim;
+use rand::Rng;
+use std::cmp::Ordering;
+
+/// Where every bot's rating starts.
+pub const START_RATING: f64 = 1500.0;
+/// The most a rating can move in one game.
+const K_FACTOR: f64 = 32.0;
+
+/// How one snake did in an arena game.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finish {
+    /// 0 for first; snakes that tied share a place.
+    pub place: usize,
+    pub score: u32,
+    /// Ticks the snake stayed on the board.
+    pub lasted: u64,
+    pub disqualified: bool,
+}
+
+/// A finished arena game with its recording.
+pub struct ArenaGame {
+    pub game: Game,
+    pub replay: Replay,
+    /// One entry per snake, in the order they played.
+    pub finishes: Vec<Finish>,
+}
+
+/// Splits `entrants` bots into arenas of `size` snakes for one round, in an
+/// order shuffled by `seed`. A bot left over on its own sits the round out.
+pub fn arenas(entrants: usize, size: usize, seed: u64) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..entrants).collect();
+    game::rng_from_seed(seed).shuffle(&mut order);
+    order
+        .chunks(size.max(2))
+        .filter(|arena| arena.len() > 1)
+        .map(|arena| arena.to_vec())
+        .collect()
+}
+
+/// Plays and records one game with a snake for each controller, as
+/// `sim::play` does, and places the snakes.
+pub fn play_arena(
+    level: &Level,
+    difficulty: Difficulty,
+    seed: u64,
+    controllers: &mut [Box<dyn Controller>],
+    max_ticks: u64,
+) -> Result<ArenaGame, String> {
+    let played = sim::play(level, difficulty, seed, controllers, max_ticks, true)?;
+    let finishes = placings(&played.game, &played.died_at);
+    Ok(ArenaGame {
+        game: played.game,
+        replay: played.replay.expect("arena games are recorded"),
+        finishes,
+    })
+}
+
+/// Places the snakes: a winner first, then whoever lasted longest, then
+/// the higher score.
+fn placings(game: &Game, died_at: &[Option<u64>]) -> Vec<Finish> {
+    let keys: Vec<(bool, u64, u32)> = game
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let won = game.outcome() == Some(Outcome::Winner(i));
+            let lasted = died_at[i].unwrap_or(u64::MAX);
+            (won, lasted, p.score.val)
+        })
+        .collect();
+    game.players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| Finish {
+            place: keys.iter().filter(|&&key| key > keys[i]).count(),
+            score: p.score.val,
+            lasted: died_at[i].unwrap_or_else(|| game.ticks()),
+            disqualified: p.death == Some(Death::Disqualified),
+        })
+        .collect()
+}
+
+/// One bot's record over the tournament so far.
+#[derive(Clone, Debug)]
+pub struct Standing {
+    pub name: String,
+    pub rating: f64,
+    pub games: u32,
+    /// Games finished alone in first place.
+    pub wins: u32,
+    pub total_score: u64,
+    pub disqualified: u32,
+}
+
+impl Standing {
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            f64::from(self.wins) / f64::from(self.games)
+        }
+    }
+    pub fn mean_score(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.total_score as f64 / f64::from(self.games)
+        }
+    }
+}
+
+/// Ratings and records for every bot entered.
+///
+/// Ratings are Elo, with each arena game of n snakes counted as a match
+/// between every pair of them, scaled down so one game moves a rating by
+/// at most `K_FACTOR` whatever the arena size.
+pub struct Tournament {
+    standings: Vec<Standing>,
+}
+
+impl Tournament {
+    pub fn new<I: IntoIterator<Item = String>>(names: I) -> Tournament {
+        Tournament {
+            standings: names
+                .into_iter()
+                .map(|name| Standing {
+                    name,
+                    rating: START_RATING,
+                    games: 0,
+                    wins: 0,
+                    total_score: 0,
+                    disqualified: 0,
+                })
+                .collect(),
+        }
+    }
+    pub fn standings(&self) -> &[Standing] {
+        &self.standings
+    }
+    /// Scores a game between `entrants`, indices into the standings, with
+    /// `finishes` in the same order.
+    pub fn record(&mut self, entrants: &[usize], finishes: &[Finish]) {
+        let n = entrants.len();
+        let mut change = vec![0.0; n];
+        for a in 0..n {
+            for b in 0..n {
+                if a == b {
+                    continue;
+                }
+                let (ra, rb) = (
+                    self.standings[entrants[a]].rating,
+                    self.standings[entrants[b]].rating,
+                );
+                let expected = 1.0 / (1.0 + 10f64.powf((rb - ra) / 400.0));
+                let actual = match finishes[a].place.cmp(&finishes[b].place) {
+                    Ordering::Less => 1.0,
+                    Ordering::Equal => 0.5,
+                    Ordering::Greater => 0.0,
+                };
+                change[a] += K_FACTOR / (n - 1) as f64 * (actual - expected);
+            }
+        }
+        for (i, finish) in finishes.iter().enumerate() {
+            let standing = &mut self.standings[entrants[i]];
+            standing.rating += change[i];
+            standing.games += 1;
+            standing.total_score += u64::from(finish.score);
+            let alone = finishes.iter().filter(|f| f.place == 0).count() == 1;
+            if finish.place == 0 && alone {
+                standing.wins += 1;
+            }
+            if finish.disqualified {
+                standing.disqualified += 1;
+            }
+        }
+    }
+    /// Indices into the standings, best rating first.
+    pub fn leaderboard(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.standings.len()).collect();
+        order.sort_by(|&a, &b| {
+            self.standings[b]
+                .rating
+                .partial_cmp(&self.standings[a].rating)
+                .unwrap()
+        });
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Bounds;
+
+    /// A game of `scores.len()` snakes with those scores, cut off at tick
+    /// 20 unless it has been decided, and snakes with a death tick
+    /// disqualified.
+    fn placed(scores: &[u32], died_at: &[Option<u64>]) -> Vec<usize> {
+        let level = Level::empty(Bounds::new(30, 30, false));
+        let mut game = Game::with_snakes(&level, scores.len(), Difficulty::Normal, 0).unwrap();
+        for (i, &score) in scores.iter().enumerate() {
+            game.players[i].score.val = score;
+            if died_at[i].is_some() {
+                game.disqualify(i);
+            }
+        }
+        placings(&game, died_at).iter().map(|f| f.place).collect()
+    }
+
+    fn finish(place: usize) -> Finish {
+        Finish {
+            place,
+            score: 0,
+            lasted: 0,
+            disqualified: false,
+        }
+    }
+
+    #[test]
+    fn ties_share_a_place() {
+        assert_eq!(placed(&[4, 4, 2], &[None; 3]), vec![0, 0, 2]);
+        assert_eq!(placed(&[3, 3, 3], &[None; 3]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn survivors_beat_snakes_that_died_together() {
+        let died = [None, None, Some(10), Some(10)];
+        assert_eq!(placed(&[1, 3, 9, 5], &died), vec![1, 0, 2, 3]);
+        // Lasting longer counts before score.
+        assert_eq!(
+            placed(&[0, 0, 9, 5], &[None, None, Some(4), Some(10)]),
+            vec![0, 0, 3, 2]
+        );
+    }
+
+    #[test]
+    fn the_winner_places_first() {
+        assert_eq!(placed(&[1, 6], &[None, Some(5)]), vec![0, 1]);
+    }
+
+    #[test]
+    fn rating_changes_sum_to_zero() {
+        let mut tournament = Tournament::new((0..5).map(|i| format!("bot {}", i)));
+        tournament.record(&[0, 1, 2, 3], &[finish(0), finish(1), finish(2), finish(3)]);
+        assert_eq!(tournament.leaderboard(), vec![0, 1, 4, 2, 3]);
+        let before: f64 = tournament.standings().iter().map(|s| s.rating).sum();
+        tournament.record(&[3, 1, 4, 0], &[finish(0), finish(1), finish(1), finish(3)]);
+        let after: f64 = tournament.standings().iter().map(|s| s.rating).sum();
+        assert!((after - before).abs() < 1e-9);
+        assert!((before - 5.0 * START_RATING).abs() < 1e-9);
+    }
+
+    #[test]
+    fn only_a_lone_first_place_is_a_win() {
+        let mut tournament = Tournament::new(vec!["a".to_string(), "b".to_string()]);
+        tournament.record(&[0, 1], &[finish(0), finish(0)]);
+        let mut out = finish(1);
+        out.disqualified = true;
+        tournament.record(&[0, 1], &[finish(0), out]);
+        let standings = tournament.standings();
+        assert_eq!((standings[0].games, standings[0].wins), (2, 1));
+        assert_eq!((standings[1].wins, standings[1].disqualified), (0, 1));
+        assert_eq!(standings[0].win_rate(), 0.5);
+    }
+
+    #[test]
+    fn every_bot_plays_once_a_round_bar_the_odd_one_out() {
+        let mut sat_out = vec![0; 7];
+        for seed in 0..50 {
+            let rounds = arenas(7, 3, seed);
+            assert_eq!(rounds.len(), 2);
+            let mut seen: Vec<usize> = rounds.concat();
+            assert!(rounds.iter().all(|arena| arena.len() == 3));
+            seen.sort();
+            seen.dedup();
+            assert_eq!(seen.len(), 6);
+            sat_out[(0..7).find(|bot| !seen.contains(bot)).unwrap()] += 1;
+
+            let mut everyone = arenas(8, 3, seed).concat();
+            everyone.sort();
+            assert_eq!(everyone, (0..8).collect::<Vec<_>>());
+        }
+        // Which bot sits out changes with the seed.
+        assert!(sat_out.iter().all(|&n| n > 0), "{:?}", sat_out);
+        assert_eq!(arenas(4, 1, 0).len(), 2);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::game::{Game, Outcome};
+
+/// Round wins in a head-to-head match, played until one snake has won
+/// `rounds_to_win` rounds.
+pub struct Match {
+    wins: Vec<u32>,
+    draws: u32,
+    rounds_to_win: u32,
+}
+
+impl Match {
+    pub fn new(players: usize, rounds_to_win: u32) -> Match {
+        Match {
+            wins: vec![0; players],
+            draws: 0,
+            rounds_to_win: rounds_to_win.max(1),
+        }
+    }
+    pub fn wins(&self) -> &[u32] {
+        &self.wins
+    }
+    pub fn draws(&self) -> u32 {
+        self.draws
+    }
+    pub fn rounds_to_win(&self) -> u32 {
+        self.rounds_to_win
+    }
+    /// Rounds finished so far.
+    pub fn rounds(&self) -> u32 {
+        self.wins.iter().sum::<u32>() + self.draws
+    }
+    /// Scores a round and returns its winner, if there was one. A full
+    /// board, or a round stopped before it was decided, goes to the highest
+    /// score among the snakes still alive.
+    pub fn record(&mut self, game: &Game) -> Option<usize> {
+        let winner = round_winner(game);
+        match winner {
+            Some(player) => self.wins[player] += 1,
+            None => self.draws += 1,
+        }
+        winner
+    }
+    /// The snake that has won the match, once one has.
+    pub fn winner(&self) -> Option<usize> {
+        self.wins.iter().position(|&w| w >= self.rounds_to_win)
+    }
+}
+
+fn round_winner(game: &Game) -> Option<usize> {
+    match game.outcome() {
+        Some(Outcome::Winner(player)) => Some(player),
+        Some(Outcome::Died) | Some(Outcome::Draw) => None,
+        Some(Outcome::BoardFull) | Some(Outcome::GoalReached) | None => {
+            let alive: Vec<usize> = (0..game.players.len())
+                .filter(|&i| game.players[i].alive)
+                .collect();
+            let best = alive.iter().map(|&i| game.players[i].score.val).max()?;
+            let mut leaders = alive
+                .into_iter()
+                .filter(|&i| game.players[i].score.val == best);
+            match (leaders.next(), leaders.next()) {
+                (Some(player), None) => Some(player),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::Difficulty;
+    use crate::game::{Bounds, Death, GridPos};
+    use crate::level::Level;
+
+    /// Two snakes head to head on the middle row, five cells apart.
+    fn facing() -> Game {
+        let level =
+            Level::parse("---\n.........\n.........\n.S.......\n.........\n.........\n").unwrap();
+        let mut game = Game::with_snakes(&level, 2, Difficulty::Normal, 0).unwrap();
+        game.apple.pos = GridPos::new(0, 0);
+        game
+    }
+
+    fn three_snakes() -> Game {
+        let level = Level::empty(Bounds::new(20, 20, false));
+        Game::with_snakes(&level, 3, Difficulty::Normal, 0).unwrap()
+    }
+
+    #[test]
+    fn heads_meeting_is_a_drawn_round() {
+        let mut game = facing();
+        while game.outcome().is_none() {
+            game.step(&[None, None]);
+        }
+        assert_eq!(game.players[0].death, Some(Death::HeadOn));
+        let mut versus = Match::new(2, 3);
+        assert_eq!(versus.record(&game), None);
+        assert_eq!((versus.wins(), versus.draws()), (&[0, 0][..], 1));
+    }
+
+    #[test]
+    fn an_undecided_round_goes_to_the_best_living_snake() {
+        let mut game = three_snakes();
+        game.disqualify(2);
+        assert_eq!(game.outcome(), None);
+        game.players[0].score.val = 3;
+        game.players[1].score.val = 5;
+        game.players[2].score.val = 9;
+        assert_eq!(round_winner(&game), Some(1));
+        game.players[0].score.val = 5;
+        assert_eq!(round_winner(&game), None);
+    }
+
+    #[test]
+    fn the_first_to_enough_rounds_wins_the_match() {
+        let mut versus = Match::new(2, 2);
+        let mut won_by = |player: usize| {
+            let mut game = facing();
+            game.disqualify(1 - player);
+            versus.record(&game)
+        };
+        assert_eq!(won_by(0), Some(0));
+        assert_eq!(won_by(1), Some(1));
+        assert_eq!(won_by(0), Some(0));
+        assert_eq!(versus.wins(), &[2, 1]);
+        assert_eq!(versus.rounds(), 3);
+        assert_eq!(versus.winner(), Some(0));
+        assert_eq!(Match::new(2, 0).rounds_to_win(), 1);
+    }
+}